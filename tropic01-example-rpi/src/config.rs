@@ -0,0 +1,90 @@
+//! Bench configuration read from environment variables
+//!
+//! | Variable                | Default                                      |
+//! |-------------------------|----------------------------------------------|
+//! | `TROPIC01_SPI_DEV`      | `/dev/spidev0.2`, else the first `spidev*`   |
+//! | `TROPIC01_SPI_HZ`       | `5000000`                                    |
+//! | `TROPIC01_PAIRING_KEY`  | the engineering sample `SH0` key             |
+//! | `TROPIC01_PAIRING_SLOT` | `0`                                          |
+//!
+//! `TROPIC01_PAIRING_KEY` is the path to a file containing the raw 32 byte
+//! X25519 private pairing key. `TROPIC01_PAIRING_SLOT` is one of the chip's
+//! pairing key slots 0 to 3.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use anyhow::anyhow;
+use tropic01::keys::SH0PRIV;
+
+const DEFAULT_SPI_DEV: &str = "/dev/spidev0.2";
+const DEFAULT_SPI_HZ: u32 = 5_000_000;
+const MAX_PAIRING_SLOT: u8 = 3;
+
+pub struct Config {
+    pub spi_dev: PathBuf,
+    pub spi_hz: u32,
+    pub pairing_key: [u8; 32],
+    pub pairing_slot: u8,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        let spi_dev = match env::var_os("TROPIC01_SPI_DEV") {
+            Some(path) => PathBuf::from(path),
+            None => discover_spi_dev()?,
+        };
+        let spi_hz = match env::var("TROPIC01_SPI_HZ") {
+            Ok(hz) => hz.parse().context("TROPIC01_SPI_HZ is not a number")?,
+            Err(_) => DEFAULT_SPI_HZ,
+        };
+        let pairing_key = match env::var_os("TROPIC01_PAIRING_KEY") {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                fs::read(&path)
+                    .with_context(|| format!("reading pairing key from {}", path.display()))?
+                    .try_into()
+                    .map_err(|_| anyhow!("pairing key is not 32 bytes long"))?
+            },
+            None => SH0PRIV,
+        };
+        let pairing_slot = match env::var("TROPIC01_PAIRING_SLOT") {
+            Ok(slot) => slot
+                .parse()
+                .ok()
+                .filter(|slot| *slot <= MAX_PAIRING_SLOT)
+                .ok_or_else(|| anyhow!("TROPIC01_PAIRING_SLOT is not a slot number (0-3)"))?,
+            Err(_) => 0,
+        };
+        Ok(Self {
+            spi_dev,
+            spi_hz,
+            pairing_key,
+            pairing_slot,
+        })
+    }
+}
+
+/// Use the default SPI device if present, otherwise the first one found.
+fn discover_spi_dev() -> Result<PathBuf, anyhow::Error> {
+    let default = PathBuf::from(DEFAULT_SPI_DEV);
+    if default.exists() {
+        return Ok(default);
+    }
+    let mut devices = fs::read_dir("/dev")?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("spidev"))
+        })
+        .collect::<Vec<_>>();
+    devices.sort();
+    devices
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no SPI device found, set TROPIC01_SPI_DEV"))
+}
@@ -5,23 +5,29 @@ use linux_embedded_hal::spidev::SpiModeFlags;
 use linux_embedded_hal::spidev::SpidevOptions;
 use rand_core::OsRng;
 use sha2::Digest as _;
+use tropic01::ChipId;
 use tropic01::EccCurve;
 use tropic01::Error;
+use tropic01::Prehash;
 use tropic01::Tropic01;
 use tropic01::X25519Dalek;
-use tropic01::keys::SH0PRIV;
 use tropic01::keys::SH0PUB;
 use x25519_dalek::PublicKey;
 use x25519_dalek::StaticSecret;
 
+use crate::config::Config;
+
+mod config;
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
+    let config = Config::from_env()?;
 
-    let mut spi_device = SpidevDevice::open("/dev/spidev0.2")?;
+    let mut spi_device = SpidevDevice::open(&config.spi_dev)?;
     spi_device.configure(
         &SpidevOptions::new()
-            .max_speed_hz(5_000_000)
+            .max_speed_hz(config.spi_hz)
             .mode(SpiModeFlags::SPI_MODE_0)
             .build(),
     )?;
@@ -37,17 +43,22 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let res = tropic01.get_info_chip_id()?;
     println!("ChipId: {res:x?}");
-    let chip_id = res.to_vec();
+    let chip_id = *res;
+    println!("{}", ChipId::try_from(&res[..])?);
 
     println!("Sleep");
     tropic01.sleep_req(tropic01::SleepReq::Sleep)?;
+    tropic01.wake()?;
+    println!("Woke up");
 
     let res = tropic01.get_info_cert()?;
-    println!("Cert: {res:x?}");
+    let mut pem = String::new();
+    res.write_pem(&mut pem)?;
+    println!("Cert:\n{pem}");
 
     println!("Reboot");
-    tropic01.startup_req(tropic01::StartupReq::Reboot)?;
-    println!("Rebooted");
+    let version = tropic01.reboot_into(tropic01::StartupReq::Reboot)?;
+    println!("Rebooted, RISC-V firmware {version}");
 
     let res = tropic01.get_info_chip_id()?;
     println!("ChipId after reboot: {res:x?}");
@@ -56,9 +67,16 @@ async fn main() -> Result<(), anyhow::Error> {
     let csprng = OsRng;
     let ehpriv = StaticSecret::random_from_rng(csprng);
     let ehpub = PublicKey::from(&ehpriv);
-    let shpub = SH0PUB.into();
-    let shpriv = SH0PRIV.into();
-    tropic01.session_start(&X25519Dalek, shpub, shpriv, ehpub, ehpriv, 0)?;
+    let shpriv = StaticSecret::from(config.pairing_key);
+    let shpub = PublicKey::from(&shpriv);
+    tropic01.session_start(
+        &X25519Dalek,
+        shpub,
+        shpriv,
+        ehpub,
+        ehpriv,
+        config.pairing_slot,
+    )?;
 
     let res = tropic01.get_random_value(6)?;
     println!("random value get: {res:x?}");
@@ -86,11 +104,14 @@ async fn main() -> Result<(), anyhow::Error> {
     let msg = "hello tropic";
     let mut hasher = sha2::Sha256::new();
     hasher.update(msg);
-    let hash: [u8; 32] = hasher.finalize().into();
-    let signature = tropic01.eddsa_sign(key_slot, &hash)?;
-    println!("signature of hash: {signature:x?}");
+    let signature = tropic01.eddsa_sign_prehashed(key_slot, &Prehash::from(hasher))?;
+    println!("signature of hash: {:x?}", signature.signature());
+    assert!(signature.is_over(msg.as_bytes()));
     public_key
-        .verify_strict(&hash, &Signature::from_bytes(signature))
+        .verify_strict(
+            signature.signed_message(),
+            &Signature::from_bytes(signature.signature()),
+        )
         .expect("signature to be verified");
 
     // Produce an unauthorized error to test nonce behavior
@@ -110,5 +131,7 @@ async fn main() -> Result<(), anyhow::Error> {
         .verify_strict(msg, &Signature::from_bytes(signature))
         .expect("signature to be verified");
 
+    println!("Transport stats: {:?}", tropic01.stats());
+
     Ok(())
 }
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tropic01::ChipId;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(chip_id) = ChipId::try_from(data) {
+        assert_eq!(data.len(), ChipId::SIZE);
+        let _ = chip_id.serial_number();
+    }
+});
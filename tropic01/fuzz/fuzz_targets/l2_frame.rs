@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tropic01::L2ResponseFrame;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frame) = L2ResponseFrame::from_bytes(data) {
+        assert!(frame.resp_data().len() <= data.len());
+        let _ = frame.check_frame();
+    }
+});
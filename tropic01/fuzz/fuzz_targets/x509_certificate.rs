@@ -0,0 +1,33 @@
+#![no_main]
+
+use core::fmt;
+
+use libfuzzer_sys::fuzz_target;
+use tropic01::X509Certificate;
+
+/// Discards the PEM output
+struct Sink;
+
+impl fmt::Write for Sink {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // The driver always passes X509Certificate::SIZE bytes, either a bare
+    // certificate followed by zeros or the start of a certificate store.
+    let mut buf = [0; X509Certificate::SIZE];
+    let len = data.len().min(buf.len());
+    buf[..len].copy_from_slice(&data[..len]);
+
+    let cert = X509Certificate::new(&buf);
+    if let Ok(der) = cert.as_der() {
+        assert!(der.len() <= X509Certificate::SIZE);
+    }
+    let _ = cert.fingerprint();
+    let _ = cert.public_key();
+    let _ = cert.serial_number();
+    let _ = cert.subject();
+    let _ = cert.write_pem(&mut Sink);
+});
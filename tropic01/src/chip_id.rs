@@ -0,0 +1,387 @@
+use core::fmt;
+
+use nom_derive::Nom;
+
+use crate::FromBytes;
+use crate::ParsingError;
+
+/// Serial number of the chip (`SER_NUM`), as part of the [ChipId].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Nom)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SerialNumber {
+    sn: u8,
+    fab_data: [u8; 3],
+    #[nom(LittleEndian)]
+    fab_date: u16,
+    lot_id: [u8; 5],
+    wafer_id: u8,
+    #[nom(LittleEndian)]
+    x_coord: u16,
+    #[nom(LittleEndian)]
+    y_coord: u16,
+}
+
+impl SerialNumber {
+    /// Size of the encoded serial number
+    pub const SIZE: usize = 16;
+
+    /// Return the serial number as it is encoded in the chip ID.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0] = self.sn;
+        bytes[1..4].copy_from_slice(&self.fab_data);
+        bytes[4..6].copy_from_slice(&self.fab_date.to_le_bytes());
+        bytes[6..11].copy_from_slice(&self.lot_id);
+        bytes[11] = self.wafer_id;
+        bytes[12..14].copy_from_slice(&self.x_coord.to_le_bytes());
+        bytes[14..16].copy_from_slice(&self.y_coord.to_le_bytes());
+        bytes
+    }
+
+    #[must_use]
+    pub const fn sn(&self) -> u8 {
+        self.sn
+    }
+
+    /// 12 bits fab ID followed by 12 bits part number ID
+    #[must_use]
+    pub const fn fab_data(&self) -> [u8; 3] {
+        self.fab_data
+    }
+
+    #[must_use]
+    pub const fn fab_date(&self) -> u16 {
+        self.fab_date
+    }
+
+    #[must_use]
+    pub const fn lot_id(&self) -> [u8; 5] {
+        self.lot_id
+    }
+
+    #[must_use]
+    pub const fn wafer_id(&self) -> u8 {
+        self.wafer_id
+    }
+
+    /// Position of the die on the wafer
+    #[must_use]
+    pub const fn coordinates(&self) -> (u16, u16) {
+        (self.x_coord, self.y_coord)
+    }
+}
+
+/// Serial code of the chip returned by [crate::Tropic01::serial_code_get].
+///
+/// [fmt::Display] writes the serial code in lowercase hex, like
+/// [fmt::LowerHex].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SerialCode([u8; SerialCode::SIZE]);
+
+impl SerialCode {
+    /// Size of the encoded serial code
+    pub const SIZE: usize = 32;
+
+    #[must_use]
+    pub const fn new(bytes: [u8; Self::SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    #[must_use]
+    pub const fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for SerialCode {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::LowerHex for SerialCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::UpperHex for SerialCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SerialCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+/// Chip identification data returned by [crate::Tropic01::chip_id].
+///
+/// See the `lt_chip_id_t` structure of the C implementation for the layout.
+#[derive(Debug, Clone, PartialEq, Eq, Nom)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[nom(Exact)]
+pub struct ChipId {
+    chip_id_ver: [u8; 4],
+    fl_chip_info: [u8; 16],
+    func_test_info: [u8; 8],
+    silicon_rev: [u8; 4],
+    packg_type_id: [u8; 2],
+    _rfu_1: [u8; 2],
+    prov_ver_fab_id_pn: [u8; 4],
+    provisioning_date: [u8; 2],
+    hsm_ver: [u8; 4],
+    prog_ver: [u8; 4],
+    _rfu_2: [u8; 2],
+    ser_num: SerialNumber,
+    part_num_data: [u8; 16],
+    prov_templ_ver: [u8; 2],
+    prov_templ_tag: [u8; 4],
+    prov_spec_ver: [u8; 2],
+    prov_spec_tag: [u8; 4],
+    batch_id: [u8; 5],
+    _rfu_3: [u8; 3],
+    _rfu_4: [u8; 24],
+}
+
+impl TryFrom<&[u8]> for ChipId {
+    type Error = ParsingError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(value)
+    }
+}
+
+impl ChipId {
+    /// Size of the encoded chip ID
+    pub const SIZE: usize = 128;
+
+    #[must_use]
+    pub const fn chip_id_ver(&self) -> [u8; 4] {
+        self.chip_id_ver
+    }
+
+    /// Factory level test info (`FL_PROD_DATA`)
+    #[must_use]
+    pub const fn fl_chip_info(&self) -> [u8; 16] {
+        self.fl_chip_info
+    }
+
+    /// Manufacturing level test info (`MAN_FUNC_TEST`)
+    #[must_use]
+    pub const fn func_test_info(&self) -> [u8; 8] {
+        self.func_test_info
+    }
+
+    #[must_use]
+    pub const fn silicon_rev(&self) -> [u8; 4] {
+        self.silicon_rev
+    }
+
+    #[must_use]
+    pub const fn packg_type_id(&self) -> [u8; 2] {
+        self.packg_type_id
+    }
+
+    /// 8 bits provisioning version, 12 bits fab ID and 12 bits part number
+    #[must_use]
+    pub const fn prov_ver_fab_id_pn(&self) -> [u8; 4] {
+        self.prov_ver_fab_id_pn
+    }
+
+    #[must_use]
+    pub const fn provisioning_date(&self) -> [u8; 2] {
+        self.provisioning_date
+    }
+
+    #[must_use]
+    pub const fn hsm_ver(&self) -> [u8; 4] {
+        self.hsm_ver
+    }
+
+    #[must_use]
+    pub const fn prog_ver(&self) -> [u8; 4] {
+        self.prog_ver
+    }
+
+    #[must_use]
+    pub const fn serial_number(&self) -> &SerialNumber {
+        &self.ser_num
+    }
+
+    /// Part number, the first byte holds the length of the following data.
+    #[must_use]
+    pub const fn part_num_data(&self) -> [u8; 16] {
+        self.part_num_data
+    }
+
+    #[must_use]
+    pub const fn prov_templ_ver(&self) -> [u8; 2] {
+        self.prov_templ_ver
+    }
+
+    #[must_use]
+    pub const fn prov_templ_tag(&self) -> [u8; 4] {
+        self.prov_templ_tag
+    }
+
+    #[must_use]
+    pub const fn prov_spec_ver(&self) -> [u8; 2] {
+        self.prov_spec_ver
+    }
+
+    #[must_use]
+    pub const fn prov_spec_tag(&self) -> [u8; 4] {
+        self.prov_spec_tag
+    }
+
+    #[must_use]
+    pub const fn batch_id(&self) -> [u8; 5] {
+        self.batch_id
+    }
+
+    /// Write a human readable report of the chip ID to `w`.
+    ///
+    /// This does not allocate and can be used to print the chip identity to
+    /// e.g. a UART console.
+    pub fn write_report<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        let ser_num = &self.ser_num;
+        let (x, y) = ser_num.coordinates();
+        write_field(w, "Chip ID version", &self.chip_id_ver)?;
+        write_field(w, "Factory level test info", &self.fl_chip_info)?;
+        write_field(w, "Manufacturing test info", &self.func_test_info)?;
+        write_field(w, "Silicon revision", &self.silicon_rev)?;
+        write_field(w, "Package type ID", &self.packg_type_id)?;
+        write_field(w, "Provisioning version/fab/PN", &self.prov_ver_fab_id_pn)?;
+        write_field(w, "Provisioning date", &self.provisioning_date)?;
+        write_field(w, "HSM version", &self.hsm_ver)?;
+        write_field(w, "Programmer version", &self.prog_ver)?;
+        write_field(w, "Serial number", &ser_num.to_bytes())?;
+        write_label(w, "  SN")?;
+        writeln!(w, "{:#04x}", ser_num.sn())?;
+        write_field(w, "  Fab data", &ser_num.fab_data())?;
+        write_label(w, "  Fab date")?;
+        writeln!(w, "{:#06x}", ser_num.fab_date())?;
+        write_field(w, "  Lot ID", &ser_num.lot_id())?;
+        write_label(w, "  Wafer ID")?;
+        writeln!(w, "{:#04x}", ser_num.wafer_id())?;
+        write_label(w, "  Coordinates (x, y)")?;
+        writeln!(w, "({x}, {y})")?;
+        write_field(w, "Part number data", &self.part_num_data)?;
+        write_field(w, "Provisioning template ver.", &self.prov_templ_ver)?;
+        write_field(w, "Provisioning template tag", &self.prov_templ_tag)?;
+        write_field(w, "Provisioning spec. version", &self.prov_spec_ver)?;
+        write_field(w, "Provisioning spec. tag", &self.prov_spec_tag)?;
+        write_field(w, "Batch ID", &self.batch_id)
+    }
+}
+
+impl fmt::Display for ChipId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_report(f)
+    }
+}
+
+/// Width of the label column in [ChipId::write_report]
+const REPORT_LABEL_WIDTH: usize = 30;
+
+/// Write the label of one line of the report, padded to align the values.
+fn write_label<W: fmt::Write>(w: &mut W, label: &str) -> fmt::Result {
+    let pad = REPORT_LABEL_WIDTH.saturating_sub(label.len() + 1);
+    write!(w, "{label}:{:pad$}", "")
+}
+
+/// Write one line of the report with `bytes` in hex.
+fn write_field<W: fmt::Write>(w: &mut W, label: &str, bytes: &[u8]) -> fmt::Result {
+    write_label(w, label)?;
+    for byte in bytes {
+        write!(w, "{byte:02x}")?;
+    }
+    writeln!(w)
+}
+
+#[cfg(test)]
+mod test {
+    use core::fmt::Write as _;
+
+    use aes_gcm::aead::arrayvec::ArrayString;
+
+    use crate::ParsingError;
+    use crate::chip_id::ChipId;
+    use crate::chip_id::SerialCode;
+
+    #[test]
+    fn chip_id_parsing_works() {
+        let mut data = [0; ChipId::SIZE];
+        data[0..4].copy_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        // SER_NUM starts at offset 52
+        data[52..68].copy_from_slice(&[
+            0x0a, 0xf0, 0x01, 0x23, 0x34, 0x12, 0x01, 0x02, 0x03, 0x04, 0x05, 0x07, 0x10, 0x00,
+            0x20, 0x00,
+        ]);
+        data[96..101].copy_from_slice(&[0xb1, 0xb2, 0xb3, 0xb4, 0xb5]);
+
+        let chip_id = ChipId::try_from(&data[..]).unwrap();
+        assert_eq!(chip_id.chip_id_ver(), [0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(chip_id.batch_id(), [0xb1, 0xb2, 0xb3, 0xb4, 0xb5]);
+
+        let ser_num = chip_id.serial_number();
+        assert_eq!(ser_num.sn(), 0x0a);
+        assert_eq!(ser_num.fab_data(), [0xf0, 0x01, 0x23]);
+        assert_eq!(ser_num.fab_date(), 0x1234);
+        assert_eq!(ser_num.lot_id(), [0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert_eq!(ser_num.wafer_id(), 0x07);
+        assert_eq!(ser_num.coordinates(), (0x10, 0x20));
+        assert_eq!(&ser_num.to_bytes()[..], &data[52..68]);
+    }
+
+    #[test]
+    fn chip_id_write_report_works() {
+        let mut data = [0; ChipId::SIZE];
+        data[96..101].copy_from_slice(&[0xb1, 0xb2, 0xb3, 0xb4, 0xb5]);
+        let chip_id = ChipId::try_from(&data[..]).unwrap();
+        let mut report = ArrayString::<2048>::new();
+        chip_id.write_report(&mut report).unwrap();
+        assert_eq!(report.lines().count(), 22);
+        assert_eq!(
+            report.lines().last(),
+            Some("Batch ID:                     b1b2b3b4b5")
+        );
+    }
+
+    #[test]
+    fn chip_id_parsing_rejects_wrong_length() {
+        let data = [0; ChipId::SIZE + 1];
+        assert!(matches!(
+            ChipId::try_from(&data[..]),
+            Err(ParsingError::Error(_))
+        ));
+        assert!(ChipId::try_from(&data[..ChipId::SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn serial_code_format_works() {
+        let mut bytes = [0; SerialCode::SIZE];
+        bytes[0] = 0xab;
+        bytes[31] = 0x01;
+        let serial_code = SerialCode::new(bytes);
+        let mut s = ArrayString::<64>::new();
+        write!(s, "{serial_code}").unwrap();
+        assert_eq!(&s[..4], "ab00");
+        assert_eq!(&s[60..], "0001");
+        s.clear();
+        write!(s, "{serial_code:X}").unwrap();
+        assert_eq!(&s[..2], "AB");
+        assert_eq!(serial_code.as_ref(), &bytes[..]);
+    }
+}
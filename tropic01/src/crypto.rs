@@ -1,6 +1,7 @@
 use aes_gcm::Aes256Gcm;
 use aes_gcm::Key;
-use aes_gcm::KeyInit as _;
+use aes_gcm::KeyInit;
+use aes_gcm::Tag;
 use aes_gcm::aead::AeadMutInPlace;
 use aes_gcm::aead::arrayvec::ArrayVec;
 use hmac::Hmac;
@@ -48,11 +49,21 @@ impl X25519 for X25519Dalek {
 /// Represents all errors that can happen during encryption and decryption of L3
 /// commands and results.
 #[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum CryptoError {
     #[display("Decryption failed: {}", _0)]
-    Decryption(#[error(not(source))] aes_gcm::Error),
+    Decryption(
+        #[error(not(source))]
+        #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+        aes_gcm::Error,
+    ),
     #[display("Encryption failed: {}", _0)]
-    Encryption(#[error(not(source))] aes_gcm::Error),
+    Encryption(
+        #[error(not(source))]
+        #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+        aes_gcm::Error,
+    ),
 }
 
 /// Cryptographic key
@@ -78,10 +89,12 @@ impl AsRef<[u8]> for CK<'_> {
 ///
 /// see section 7.4.1 of the datasheet
 pub(super) fn hkdf(ck: CK<'_>, input: &[u8]) -> ([u8; 33], [u8; 32]) {
+    /// HMAC pads keys shorter than the hash's block size with zeros, doing so
+    /// up front avoids the fallible `new_from_slice`.
     fn hmac_sha256(key: &[u8], msg: &[u8]) -> [u8; 32] {
-        let mut mac = <HmacSha256 as Mac>::new_from_slice(key)
-        // Safety: Expect is safe here because `key` is either 32 or 33 bytes long and `new_from_slice` is not panicking on those.
-        .expect("key to be 32 bytes");
+        let mut block = hmac::digest::Key::<HmacSha256>::default();
+        block.iter_mut().zip(key).for_each(|(b, k)| *b = *k);
+        let mut mac = <HmacSha256 as KeyInit>::new(&block);
         mac.update(msg);
         let result = mac.finalize();
         result.into_bytes().into()
@@ -91,9 +104,8 @@ pub(super) fn hkdf(ck: CK<'_>, input: &[u8]) -> ([u8; 33], [u8; 32]) {
     let tmp = hmac_sha256(ck.as_ref(), input);
     let output_1 = hmac_sha256(&tmp, &one);
     let mut helper: [u8; 33] = [0; 33];
-    let (left, right) = helper.split_at_mut(32);
-    left.copy_from_slice(&output_1);
-    right[0] = 2;
+    helper[..32].copy_from_slice(&output_1);
+    helper[32] = 2;
     let output_2 = hmac_sha256(&tmp, &helper);
     helper[32] = 0;
     (helper, output_2)
@@ -146,7 +158,7 @@ pub(super) fn aesgcm_encrypt(
     aad: &[u8],
     buf: &mut ArrayVec<u8, L3_FRAME_MAX_SIZE>,
 ) -> Result<[u8; 16], CryptoError> {
-    let nonce = nonce.as_ref().into();
+    let nonce = &nonce.to_bytes().into();
     let key = Key::<Aes256Gcm>::from_slice(key.as_ref());
     let mut cipher = Aes256Gcm::new(key);
 
@@ -163,10 +175,11 @@ pub(super) fn aesgcm_decrypt(
     tag: &[u8],
     buf: &mut [u8],
 ) -> Result<(), CryptoError> {
-    let nonce = nonce.as_ref().into();
+    let nonce = &nonce.to_bytes().into();
     let key = Key::<Aes256Gcm>::from_slice(key.as_ref());
     let mut cipher = Aes256Gcm::new(key);
-    let tag = tag.into();
+    let tag = &Tag::from_exact_iter(tag.iter().copied())
+        .ok_or(CryptoError::Decryption(aes_gcm::Error))?;
     cipher
         .decrypt_in_place_detached(nonce, aad, buf, tag)
         .map_err(CryptoError::Decryption)
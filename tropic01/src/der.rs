@@ -0,0 +1,194 @@
+//! Minimal ASN.1 DER reader
+//!
+//! Only supports the subset of DER needed to walk the X.509 certificate of the
+//! chip: single byte tags and definite lengths of up to two bytes.
+
+pub(super) const TAG_BOOLEAN: u8 = 0x01;
+pub(super) const TAG_INTEGER: u8 = 0x02;
+pub(super) const TAG_BIT_STRING: u8 = 0x03;
+pub(super) const TAG_OCTET_STRING: u8 = 0x04;
+pub(super) const TAG_OID: u8 = 0x06;
+pub(super) const TAG_SEQUENCE: u8 = 0x30;
+pub(super) const TAG_SET: u8 = 0x31;
+/// Context specific, constructed tag `[0]`
+pub(super) const TAG_CONTEXT_0: u8 = 0xa0;
+/// Context specific, primitive tag `[1]`
+pub(super) const TAG_IMPLICIT_1: u8 = 0x81;
+/// Context specific, primitive tag `[2]`
+pub(super) const TAG_IMPLICIT_2: u8 = 0x82;
+/// Context specific, constructed tag `[3]`
+pub(super) const TAG_CONTEXT_3: u8 = 0xa3;
+
+/// Represents all errors that can happen while reading DER encoded data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum DerError {
+    #[display("DER data ended unexpectedly")]
+    Truncated,
+    #[display("Unexpected DER tag: {_0:#04x}")]
+    UnexpectedTag(#[error(not(source))] u8),
+    #[display("Unsupported DER length encoding")]
+    UnsupportedLength,
+}
+
+/// A single DER tag-length-value item.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Tlv<'a> {
+    pub tag: u8,
+    pub value: &'a [u8],
+}
+
+/// Sequential reader over DER encoded items.
+#[derive(Debug, Clone)]
+pub(super) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Return the tag of the next item without consuming it.
+    pub const fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next item, whatever its tag.
+    pub fn read_any(&mut self) -> Result<Tlv<'a>, DerError> {
+        let (tag, header_len, value_len) = header(self.data)?;
+        let end = header_len + value_len;
+        let value = self.data.get(header_len..end).ok_or(DerError::Truncated)?;
+        self.data = self.data.get(end..).ok_or(DerError::Truncated)?;
+        Ok(Tlv { tag, value })
+    }
+
+    /// Read the next item and return its value, if it has the expected tag.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], DerError> {
+        let tlv = self.read_any()?;
+        if tlv.tag != tag {
+            return Err(DerError::UnexpectedTag(tlv.tag));
+        }
+        Ok(tlv.value)
+    }
+
+    /// Read the next item and return its value, if it has the given tag.
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, DerError> {
+        if self.peek_tag() == Some(tag) {
+            return self.read(tag).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Skip the next item, if it has the given tag.
+    pub fn skip_optional(&mut self, tag: u8) -> Result<(), DerError> {
+        self.read_optional(tag).map(|_| ())
+    }
+}
+
+/// Return the complete encoding (header and value) of the DER SEQUENCE at the
+/// start of `data`, dropping any trailing bytes.
+pub(super) fn first_item(data: &[u8]) -> Result<&[u8], DerError> {
+    data.get(..encoded_len(data)?).ok_or(DerError::Truncated)
+}
+
+/// Return the length of the complete encoding (header and value) of the DER
+/// SEQUENCE at the start of `data`.
+///
+/// Only the header needs to be present in `data`. Fails with
+/// [DerError::UnexpectedTag] if `data` does not start with a SEQUENCE, so
+/// arbitrary leading bytes are not mistaken for a DER header.
+pub(super) fn encoded_len(data: &[u8]) -> Result<usize, DerError> {
+    let (tag, header_len, value_len) = header(data)?;
+    if tag != TAG_SEQUENCE {
+        return Err(DerError::UnexpectedTag(tag));
+    }
+    Ok(header_len + value_len)
+}
+
+/// Parse the header of the DER item at the start of `data`.
+///
+/// Returns the tag, the length of the header and the length of the value.
+fn header(data: &[u8]) -> Result<(u8, usize, usize), DerError> {
+    let [tag, len, rest @ ..] = data else {
+        return Err(DerError::Truncated);
+    };
+    match *len {
+        len @ 0x00..=0x7f => Ok((*tag, 2, usize::from(len))),
+        0x81 => {
+            let len = *rest.first().ok_or(DerError::Truncated)?;
+            Ok((*tag, 3, usize::from(len)))
+        },
+        0x82 => {
+            let [hi, lo, ..] = rest else {
+                return Err(DerError::Truncated);
+            };
+            Ok((*tag, 4, usize::from(u16::from_be_bytes([*hi, *lo]))))
+        },
+        _ => Err(DerError::UnsupportedLength),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::der::DerError;
+    use crate::der::Reader;
+    use crate::der::TAG_INTEGER;
+    use crate::der::TAG_SEQUENCE;
+    use crate::der::encoded_len;
+    use crate::der::first_item;
+
+    #[test]
+    fn read_nested_items_works() {
+        let data = [0x30, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x07, 0xff];
+        let mut reader = Reader::new(&data);
+        let mut seq = Reader::new(reader.read(TAG_SEQUENCE).unwrap());
+        assert_eq!(seq.read(TAG_INTEGER).unwrap(), &[0x05]);
+        assert_eq!(seq.read(TAG_INTEGER).unwrap(), &[0x07]);
+        assert_eq!(seq.peek_tag(), None);
+        assert_eq!(reader.peek_tag(), Some(0xff));
+    }
+
+    #[test]
+    fn read_long_form_length_works() {
+        let mut data = [0u8; 4 + 0x0102];
+        data[..4].copy_from_slice(&[0x04, 0x82, 0x01, 0x02]);
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.read(0x04).unwrap().len(), 0x0102);
+    }
+
+    #[test]
+    fn first_item_drops_trailing_bytes() {
+        let data = [0x30, 0x03, 0x02, 0x01, 0x05, 0x00, 0x00];
+        assert_eq!(first_item(&data).unwrap(), &data[..5]);
+        assert_eq!(first_item(&data[..4]), Err(DerError::Truncated));
+    }
+
+    #[test]
+    fn encoded_len_only_needs_header() {
+        assert_eq!(encoded_len(&[0x30, 0x82, 0x01, 0xc2]), Ok(0x01c6));
+        assert_eq!(encoded_len(&[0x30, 0x81]), Err(DerError::Truncated));
+        assert_eq!(
+            encoded_len(&[0x01, 0x04, 0x01, 0xc2]),
+            Err(DerError::UnexpectedTag(0x01))
+        );
+    }
+
+    #[test]
+    fn read_truncated_item_fails() {
+        let data = [0x30, 0x05, 0x02, 0x01];
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.read(TAG_SEQUENCE), Err(DerError::Truncated));
+    }
+
+    #[test]
+    fn read_unexpected_tag_fails() {
+        let data = [0x02, 0x01, 0x00];
+        let mut reader = Reader::new(&data);
+        assert_eq!(
+            reader.read(TAG_SEQUENCE),
+            Err(DerError::UnexpectedTag(TAG_INTEGER))
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Internal logging macros
+//!
+//! The log macros forward to [defmt] when the `defmt` feature is enabled and
+//! emit [tracing] events when the `tracing` feature is enabled. Their format
+//! strings are limited to what both accept, i.e. `{}` and display hints like
+//! `{:#04x}` without defmt type hints. `record!` sets a field of the current
+//! [tracing] span when the `tracing` feature is enabled. All of them compile
+//! to nothing otherwise.
+
+macro_rules! trace {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::trace!($s $(, $x)*);
+        #[cfg(feature = "tracing")]
+        ::tracing::trace!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "tracing")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+macro_rules! debug {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($s $(, $x)*);
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "tracing")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+macro_rules! warn {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::warn!($s $(, $x)*);
+        #[cfg(feature = "tracing")]
+        ::tracing::warn!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "tracing")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+macro_rules! record {
+    ($field:literal, $value:expr) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::Span::current().record($field, $value);
+        #[cfg(not(feature = "tracing"))]
+        let _ = &$value;
+    }};
+}
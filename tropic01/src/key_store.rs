@@ -0,0 +1,159 @@
+//! Hardware agnostic key store abstraction
+//!
+//! [HardwareKeyStore] covers the key slot operations applications typically
+//! need. Application code written against the trait can be tested with a
+//! software implementation and run on the chip via the [Tropic01]
+//! implementation without changes.
+
+use core::array::TryFromSliceError;
+
+use embedded_hal::digital::ErrorType as GpioErrorType;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::ErrorType as SpiErrorType;
+use embedded_hal::spi::SpiDevice;
+use sha2::Digest as _;
+use sha2::Sha256;
+use zerocopy::big_endian::U16;
+
+use crate::EccCurve;
+use crate::EccKeyReadResponse;
+use crate::Error;
+use crate::Tropic01;
+use crate::X509Certificate;
+
+/// Public key of a key slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlotPublicKey {
+    /// Raw Ed25519 public key
+    Ed25519([u8; 32]),
+    /// Uncompressed P-256 public key (x and y coordinates)
+    P256([u8; 64]),
+}
+
+impl SlotPublicKey {
+    #[must_use]
+    pub const fn curve(&self) -> EccCurve {
+        match self {
+            Self::Ed25519(_) => EccCurve::Ed25519,
+            Self::P256(_) => EccCurve::P256,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Ed25519(key) => key,
+            Self::P256(key) => key,
+        }
+    }
+}
+
+impl TryFrom<EccKeyReadResponse<'_>> for SlotPublicKey {
+    type Error = TryFromSliceError;
+
+    /// Fails if the length of the key does not match its curve.
+    fn try_from(key: EccKeyReadResponse<'_>) -> Result<Self, Self::Error> {
+        Ok(match key.curve() {
+            EccCurve::Ed25519 => Self::Ed25519(key.pub_key().try_into()?),
+            EccCurve::P256 => Self::P256(key.pub_key().try_into()?),
+        })
+    }
+}
+
+impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
+    /// Read the public key in `slot`, see [Self::ecc_key_read].
+    pub(crate) fn slot_public_key(
+        &mut self,
+        slot: U16,
+    ) -> Result<SlotPublicKey, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        self.ecc_key_read(slot)?
+            .try_into()
+            .map_err(|_| Error::InvalidL2Response)
+    }
+}
+
+/// Store of asymmetric keys addressed by slot.
+pub trait HardwareKeyStore {
+    type Error;
+    /// Evidence of the identity of the key store, see [Self::attest].
+    type Attestation<'a>
+    where
+        Self: 'a;
+
+    /// Generate a new key pair on `curve` in `slot`.
+    fn generate(&mut self, slot: U16, curve: EccCurve) -> Result<(), Self::Error>;
+
+    /// Sign `msg` with the key in `slot`.
+    ///
+    /// Ed25519 keys sign `msg` directly, P-256 keys sign its SHA-256 hash.
+    /// The signature is the raw `R || S` encoding for both curves.
+    fn sign(&mut self, slot: U16, msg: &[u8]) -> Result<[u8; 64], Self::Error>;
+
+    /// Return the public key of the key in `slot`.
+    fn public_key(&mut self, slot: U16) -> Result<SlotPublicKey, Self::Error>;
+
+    /// Erase the key in `slot`.
+    fn delete(&mut self, slot: U16) -> Result<(), Self::Error>;
+
+    /// Return evidence of the identity of the key store.
+    fn attest(&mut self) -> Result<Self::Attestation<'_>, Self::Error>;
+}
+
+impl<SPI: SpiDevice, CS: OutputPin> HardwareKeyStore for Tropic01<SPI, CS> {
+    type Error = Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>;
+    /// The device certificate of the chip
+    type Attestation<'a>
+        = X509Certificate<'a>
+    where
+        Self: 'a;
+
+    fn generate(&mut self, slot: U16, curve: EccCurve) -> Result<(), Self::Error> {
+        self.ecc_key_generate(slot, curve)
+    }
+
+    fn sign(&mut self, slot: U16, msg: &[u8]) -> Result<[u8; 64], Self::Error> {
+        let signature = match self.ecc_key_read(slot)?.curve() {
+            EccCurve::Ed25519 => self.eddsa_sign(slot, msg)?,
+            EccCurve::P256 => self.ecdsa_sign(slot, &Sha256::digest(msg).into())?,
+        };
+        Ok(*signature)
+    }
+
+    fn public_key(&mut self, slot: U16) -> Result<SlotPublicKey, Self::Error> {
+        self.slot_public_key(slot)
+    }
+
+    fn delete(&mut self, slot: U16) -> Result<(), Self::Error> {
+        self.ecc_key_erase(slot)
+    }
+
+    fn attest(&mut self) -> Result<Self::Attestation<'_>, Self::Error> {
+        self.get_info_cert()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::EccCurve;
+    use crate::EccKeyReadResponse;
+    use crate::FromBytes;
+    use crate::key_store::SlotPublicKey;
+
+    #[test]
+    fn slot_public_key_from_read_response_works() {
+        let mut data = [0; 2 + 13 + 64];
+        data[0] = EccCurve::P256 as u8;
+        data[1] = 0x01;
+        data[15..].fill(0x5a);
+        let key = SlotPublicKey::try_from(EccKeyReadResponse::from_bytes(&data).unwrap()).unwrap();
+        assert_eq!(key, SlotPublicKey::P256([0x5a; 64]));
+        assert_eq!(key.curve(), EccCurve::P256);
+
+        data[0] = EccCurve::Ed25519 as u8;
+        let key = SlotPublicKey::try_from(EccKeyReadResponse::from_bytes(&data).unwrap()).unwrap();
+        assert_eq!(key, SlotPublicKey::Ed25519([0x5a; 32]));
+        assert_eq!(key.as_bytes(), &[0x5a; 32]);
+    }
+}
@@ -1,17 +1,23 @@
 //! Engineering sample key pairs
 //!
 //! These key pairs are only useful for use with the engineering samples.
+//! Other pairing keys can be turned into constants at compile time with
+//! [crate::key_from_hex], [crate::private_key_from_pem] and
+//! [crate::public_key_from_pem].
+
+use crate::private_key_from_pem;
+use crate::public_key_from_pem;
 
 /// Keys with access to write ECC key in slot 0
-pub const SH0PRIV: [u8; 32] = [
-    0xd0, 0x99, 0x92, 0xb1, 0xf1, 0x7a, 0xbc, 0x4d, 0xb9, 0x37, 0x17, 0x68, 0xa2, 0x7d, 0xa0, 0x5b,
-    0x18, 0xfa, 0xb8, 0x56, 0x13, 0xa7, 0x84, 0x2c, 0xa6, 0x4c, 0x79, 0x10, 0xf2, 0x2e, 0x71, 0x6b,
-];
+pub const SH0PRIV: [u8; 32] = match private_key_from_pem(include_str!("keys/sh0priv.pem")) {
+    Ok(key) => key,
+    Err(_) => panic!("invalid keys/sh0priv.pem"),
+};
 /// Keys with access to write ECC key in slot 0
-pub const SH0PUB: [u8; 32] = [
-    0xe7, 0xf7, 0x35, 0xba, 0x19, 0xa3, 0x3f, 0xd6, 0x73, 0x23, 0xab, 0x37, 0x26, 0x2d, 0xe5, 0x36,
-    0x08, 0xca, 0x57, 0x85, 0x76, 0x53, 0x43, 0x52, 0xe1, 0x8f, 0x64, 0xe6, 0x13, 0xd3, 0x8d, 0x54,
-];
+pub const SH0PUB: [u8; 32] = match public_key_from_pem(include_str!("keys/sh0pub.pem")) {
+    Ok(key) => key,
+    Err(_) => panic!("invalid keys/sh0pub.pem"),
+};
 
 /// Keys with access to write ECC key in slot 1
 // TODO do not seem to work
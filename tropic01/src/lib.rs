@@ -1,8 +1,18 @@
 #![no_std]
 #![forbid(clippy::std_instead_of_alloc, clippy::std_instead_of_core)]
+// A panic bricks the firmware using the driver, errors are returned instead.
+#![deny(
+    clippy::expect_used,
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::todo,
+    clippy::unimplemented,
+    clippy::unreachable
+)]
 
 use aes_gcm::aead::arrayvec::ArrayVec;
 use dummy_pin::DummyPin;
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::ErrorType as GpioErrorType;
 use embedded_hal::digital::OutputPin;
 use embedded_hal::spi::ErrorType as SpiErrorType;
@@ -11,13 +21,31 @@ use nom::Needed;
 use nom_derive::Parse;
 use packed_struct::PackingError;
 use packed_struct::derive::PackedStruct;
-use zerocopy::IntoBytes;
 use zeroize::Zeroize;
 
+pub use crate::chip_id::ChipId;
+pub use crate::chip_id::SerialCode;
+pub use crate::chip_id::SerialNumber;
 pub use crate::crypto::CryptoError;
 pub use crate::crypto::X25519;
 #[cfg(feature = "x25519-dalek")]
 pub use crate::crypto::X25519Dalek;
+pub use crate::der::DerError;
+pub use crate::key_parse::KeyParseError;
+pub use crate::key_parse::key_from_hex;
+pub use crate::key_parse::private_key_from_pem;
+pub use crate::key_parse::public_key_from_pem;
+pub use crate::key_store::HardwareKeyStore;
+pub use crate::key_store::SlotPublicKey;
+pub use crate::lt_2::Extension;
+pub use crate::lt_2::Extensions;
+pub use crate::lt_2::FirmwareVersion;
+pub use crate::lt_2::L2ResponseContext;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub use crate::lt_2::L2ResponseFrame;
+pub use crate::lt_2::PemError;
+pub use crate::lt_2::PublicKeyError;
 pub use crate::lt_2::ResponseStatus;
 pub use crate::lt_2::SleepReq;
 pub use crate::lt_2::StartupReq;
@@ -25,17 +53,64 @@ pub use crate::lt_2::X509Certificate;
 pub use crate::lt_3::EccCurve;
 pub use crate::lt_3::EccKeyReadResponse;
 pub use crate::lt_3::EccOrigin;
+pub use crate::pinning::Fingerprint;
+pub use crate::pinning::PinSet;
+pub use crate::pinning::PinSetFull;
+pub use crate::prehash::Prehash;
+pub use crate::prehash::PrehashedSignature;
+pub use crate::retry::BusyPoll;
+pub use crate::retry::CommandClass;
+pub use crate::retry::RetryPolicy;
+pub use crate::retry::TimeoutProfile;
+#[cfg(feature = "rand_core")]
+pub use crate::rng::RNG_ERROR_CODE;
+#[cfg(feature = "rand_core")]
+pub use crate::rng::TropicRng;
+#[cfg(feature = "p256")]
+pub use crate::signer::P256SlotSigner;
+#[cfg(feature = "ed25519")]
+pub use crate::signer::SlotSigner;
+pub use crate::ssh::SSH_NAMESPACE_MAX_LEN;
+pub use crate::ssh::SshSignature;
+pub use crate::ssh::ssh_signed_data;
+pub use crate::stats::TransportStats;
 
+#[macro_use]
+mod fmt;
+
+mod chip_id;
 mod crc16;
 mod crypto;
+mod der;
+mod key_parse;
+mod key_store;
 #[cfg(feature = "keys")]
 pub mod keys;
 mod lt_1;
 mod lt_2;
 mod lt_3;
+#[cfg(test)]
+mod mock;
+mod pinning;
+mod prehash;
+mod retry;
+#[cfg(feature = "rand_core")]
+mod rng;
+#[cfg(any(feature = "ed25519", feature = "p256"))]
+mod signer;
+mod ssh;
+mod stats;
 
 /// Max number of retries when reading from chip
-const L1_READ_MAX_TRIES: usize = 50;
+const L1_READ_MAX_TRIES: u16 = 50;
+/// Delay between two polls of the chip status
+const L1_POLL_DELAY_NS: u32 = 25_000_000;
+/// Time the chip is kept unpowered by [Tropic01::power_cycle]
+const POWER_OFF_TIME_NS: u32 = 10_000_000;
+/// Time the chip needs to start up after being powered
+const POWER_UP_TIME_NS: u32 = 25_000_000;
+/// Time the chip needs to reboot after [Tropic01::startup_req]
+const REBOOT_TIME_NS: u32 = 25_000_000;
 /// Max number of data bytes in one L1 transfer
 const _L1_LEN_MAX: usize = 1 + 1 + 1 + L2_CHUNK_MAX_DATA_SIZE + 2;
 
@@ -67,6 +142,51 @@ pub struct Tropic01<SPI, CS> {
     l3_buf: ArrayVec<u8, L3_FRAME_MAX_SIZE>,
     cs: Option<CS>,
     session: Option<Session>,
+    pins: Option<PinSet>,
+    config: Config,
+    cache: Cache,
+    stats: TransportStats,
+}
+
+/// Runtime configuration, set via the `with_*` methods of [Tropic01].
+#[derive(Debug, Clone, Copy, Default)]
+struct Config {
+    retry: RetryPolicy,
+    busy_poll: BusyPoll,
+    timeouts: TimeoutProfile,
+    command_delay_ns: u32,
+    authenticated_sessions: bool,
+}
+
+impl Config {
+    /// Return the configuration used while waiting for the result of an L3
+    /// command of `class`.
+    const fn for_command(self, class: CommandClass) -> Self {
+        match self.timeouts.busy_poll(class) {
+            Some(busy_poll) => Self { busy_poll, ..self },
+            None => self,
+        }
+    }
+}
+
+/// Chip identity read once and kept until [Tropic01::invalidate_cache].
+#[derive(Debug, Clone, Default)]
+struct Cache {
+    chip_id: Option<ChipId>,
+    cert: Option<CertIdentity>,
+}
+
+/// Identity taken from the chip's device certificate
+#[derive(Debug, Clone, Copy)]
+struct CertIdentity {
+    stpub: [u8; 32],
+    /// Only needed with a [PinSet] and by [Tropic01::cert_fingerprint], which
+    /// report the error.
+    fingerprint: Result<Fingerprint, DerError>,
+    /// Chip serial number the certificate was issued for, if it names one
+    ///
+    /// Only needed by [Tropic01::verify_identity], which reports the error.
+    serial_number: Result<Option<[u8; SerialNumber::SIZE]>, DerError>,
 }
 
 impl<SPI: SpiDevice> Tropic01<SPI, DummyPin> {
@@ -85,6 +205,10 @@ impl<SPI: SpiDevice> Tropic01<SPI, DummyPin> {
             l3_buf: ArrayVec::new(),
             cs: None,
             session: None,
+            pins: None,
+            config: Config::default(),
+            cache: Cache::default(),
+            stats: TransportStats::new(),
         }
     }
 }
@@ -106,8 +230,168 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
             l3_buf: self.l3_buf,
             cs: Some(cs),
             session: self.session,
+            pins: self.pins,
+            config: self.config,
+            cache: self.cache,
+            stats: self.stats,
         })
     }
+
+    /// Only start secure sessions with chips whose certificate fingerprint is
+    /// part of `pins`.
+    ///
+    /// See [Self::session_start].
+    #[must_use]
+    pub fn with_pin_set(mut self, pins: PinSet) -> Self {
+        self.pins = Some(pins);
+        self
+    }
+
+    /// Refuse to start secure sessions that are not authenticated.
+    ///
+    /// With this, [Self::session_start] fails with
+    /// [Error::UnauthenticatedSession] unless a [PinSet] was configured with
+    /// [Self::with_pin_set].
+    #[must_use]
+    pub const fn with_authenticated_sessions(mut self) -> Self {
+        self.config.authenticated_sessions = true;
+        self
+    }
+
+    /// Retry failed L2 requests according to `retry`.
+    ///
+    /// See [RetryPolicy] for the requests the policy applies to.
+    #[must_use]
+    pub const fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    /// Poll the chip status according to `busy_poll` while the chip is busy.
+    ///
+    /// Defaults to 50 polls, 25ms apart.
+    #[must_use]
+    pub const fn with_busy_poll(mut self, busy_poll: BusyPoll) -> Self {
+        self.config.busy_poll = busy_poll;
+        self
+    }
+
+    /// Poll the results of slow L3 commands, e.g. key generation, according
+    /// to the budgets in `timeouts` instead of the general [BusyPoll].
+    ///
+    /// Defaults to the general [BusyPoll] for all commands.
+    #[must_use]
+    pub const fn with_timeout_profile(mut self, timeouts: TimeoutProfile) -> Self {
+        self.config.timeouts = timeouts;
+        self
+    }
+
+    /// Wait `delay_ns` nanoseconds before sending each command to the chip.
+    ///
+    /// Defaults to no delay.
+    #[must_use]
+    pub const fn with_command_delay_ns(mut self, delay_ns: u32) -> Self {
+        self.config.command_delay_ns = delay_ns;
+        self
+    }
+
+    /// Drop the cached chip ID and certificate identity.
+    ///
+    /// [Self::chip_id], [Self::stpub], [Self::cert_fingerprint] and
+    /// [Self::session_start] read the chip's identity from the bus once and
+    /// reuse it afterwards. Call this if the chip behind the bus may have been
+    /// replaced.
+    pub fn invalidate_cache(&mut self) {
+        self.cache = Cache::default();
+    }
+
+    /// Return the counters of the traffic with the chip and of the transport
+    /// errors since creation or the last [Self::reset_stats].
+    #[must_use]
+    pub const fn stats(&self) -> TransportStats {
+        self.stats
+    }
+
+    /// Reset all counters of [Self::stats] to 0.
+    pub const fn reset_stats(&mut self) {
+        self.stats = TransportStats::new();
+    }
+
+    /// Power cycle the chip via its power enable pin `power`, which powers the
+    /// chip when set high.
+    ///
+    /// This recovers chips stuck in alarm mode or busy states. The chip is
+    /// kept unpowered for 10ms and given 25ms to start up afterwards, waiting
+    /// with `delay` while chip select stays deasserted. The secure session is
+    /// lost, see [Self::session_start].
+    pub fn power_cycle<P: OutputPin, D: DelayNs>(
+        &mut self,
+        power: &mut P,
+        delay: &mut D,
+    ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <P as GpioErrorType>::Error>> {
+        self.session = None;
+        power.set_low().map_err(Error::GPIOError)?;
+        delay.delay_ns(POWER_OFF_TIME_NS);
+        power.set_high().map_err(Error::GPIOError)?;
+        delay.delay_ns(POWER_UP_TIME_NS);
+        Ok(())
+    }
+
+    /// Try to bring a chip in alarm mode back into a usable state.
+    ///
+    /// The chip reports alarm mode via [Error::AlarmMode]. It only leaves
+    /// alarm mode when power cycled, so this power cycles the chip via
+    /// `power` and `delay` (see [Self::power_cycle]), drops the cached chip
+    /// identity, waits for the chip to be ready and starts a new secure
+    /// session with `keys` (see [Self::session_start]).
+    ///
+    /// Fails with [Error::AlarmMode] if the chip is still in alarm mode after
+    /// the power cycle. Repeated alarms usually mean the chip detected a
+    /// physical attack or a fault, applications should not retry forever.
+    pub fn recover_from_alarm<X: X25519, P: OutputPin, D: DelayNs>(
+        &mut self,
+        power: &mut P,
+        delay: &mut D,
+        x25519: &X,
+        keys: SessionKeys<X>,
+    ) -> Result<(), RecoveryError<SPI, CS, P>> {
+        warn!("Recovering from alarm mode");
+        self.power_cycle(power, delay)
+            .map_err(|err| err.map_gpio_error(PinError::Power))?;
+        self.invalidate_cache();
+        self.wake()
+            .map_err(|err| err.map_gpio_error(PinError::ChipSelect))?;
+        let SessionKeys {
+            shipub,
+            shipriv,
+            ehpub,
+            ehpriv,
+            pkey_index,
+        } = keys;
+        self.session_start(x25519, shipub, shipriv, ehpub, ehpriv, pkey_index)
+            .map_err(|err| err.map_gpio_error(PinError::ChipSelect))
+    }
+}
+
+/// Error of [Tropic01::recover_from_alarm]
+type RecoveryError<SPI, CS, P> = Error<
+    <SPI as SpiErrorType>::Error,
+    PinError<<CS as GpioErrorType>::Error, <P as GpioErrorType>::Error>,
+>;
+
+/// Keys to start a secure session with, see [Tropic01::recover_from_alarm]
+/// and the arguments of [Tropic01::session_start].
+pub struct SessionKeys<X: X25519> {
+    /// Host public key corresponding to slot `pkey_index`
+    pub shipub: X::PublicKey,
+    /// Host private key corresponding to slot `pkey_index`
+    pub shipriv: X::StaticSecret,
+    /// Ephemeral public key
+    pub ehpub: X::PublicKey,
+    /// Ephemeral private key
+    pub ehpriv: X::StaticSecret,
+    /// Pairing key slot of the host key
+    pub pkey_index: u8,
 }
 
 #[derive(Debug, PackedStruct)]
@@ -123,11 +407,21 @@ struct ChipStatus {
 
 /// Represents all kinds of parsing errors.
 #[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum ParsingError {
     #[display("Parsing failed: {_0:?}")]
-    Error(#[error(not(source))] nom::error::ErrorKind),
+    Error(
+        #[error(not(source))]
+        #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+        nom::error::ErrorKind,
+    ),
     #[display("Parsing failed, needs more bytes: {_0:?}")]
-    Needed(#[error(not(source))] Needed),
+    Needed(
+        #[error(not(source))]
+        #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+        Needed,
+    ),
 }
 
 impl From<nom::Err<ParsingError>> for ParsingError {
@@ -155,14 +449,33 @@ impl<'a, T: Parse<&'a [u8]>> FromBytes<'a> for T {
     }
 }
 
+/// Error of one of the pins used by [Tropic01::recover_from_alarm]
+#[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum PinError<ECs, EPower> {
+    #[display("Chip select pin failed: {_0}")]
+    ChipSelect(ECs),
+    #[display("Power enable pin failed: {_0}")]
+    Power(EPower),
+}
+
 /// Any type of error which may occur while interacting with the device
 #[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error<ESpi, EGpio> {
+    /// The chip entered alarm mode, see [Tropic01::recover_from_alarm]
+    ///
+    /// The cause of the alarm is not exposed over SPI, the chip only signals
+    /// alarm mode in its status byte.
     #[display("Chip is in alarm mode")]
     AlarmMode,
     /// Some error originating from the communication bus
     #[display("L1 communication failed because of SPI bus: {_0}")]
     BusError(ESpi),
+    #[display("Chip certificate does not match any pinned fingerprint")]
+    CertificatePinMismatch,
     #[display("Chip seems to be busy")]
     ChipBusy,
     #[display("Error during decryption of the result")]
@@ -173,8 +486,14 @@ pub enum Error<ESpi, EGpio> {
     GPIOError(EGpio),
     #[display("Handshake failed")]
     HandshakeFailed,
+    #[display("Chip ID does not match the identity in the chip certificate")]
+    IdentityMismatch,
     #[display("Unexpected chip status: {_0:?}")]
-    InvalidChipStatus(#[error(not(source))] PackingError),
+    InvalidChipStatus(
+        #[error(not(source))]
+        #[cfg_attr(feature = "defmt", defmt(Debug2Format))]
+        PackingError,
+    ),
     #[display("Chip send response with invalid CRC")]
     InvalidCRC,
     #[display("The key in the requested slot does not exist")]
@@ -184,26 +503,97 @@ pub enum Error<ESpi, EGpio> {
          or a generic error code from the chip)"
     )]
     InvalidL2Response,
+    #[display("The key in the requested slot uses a different curve")]
+    InvalidCurve,
     #[display("Chip has received an invalid L3 command")]
     InvalidL3Cmd,
     #[display("Invalid public key in chip certificate")]
     InvalidPublicKey,
+    /// The chip answered a request with an L2 error status, e.g. while
+    /// receiving the result of an L3 command
+    ///
+    /// Before the error context was added, L2 error statuses of L3 commands
+    /// were returned as [Error::L3CmdFailed].
     #[display("Error during processing of L2 cmd: {_0}")]
-    L2ResponseError(ResponseStatus),
+    L2ResponseError(#[error(not(source))] L2ResponseContext),
+    /// The chip processed an L3 command but reported a failed result, or the
+    /// result spanned more L2 frames than an L3 result can fill
     #[display("Error during processing of L3 cmd")]
     L3CmdFailed,
     #[display("L3 response buffer overflow")]
     L3ResponseBufferOverflow,
+    #[display("Malformed chip certificate: {_0}")]
+    MalformedCertificate(DerError),
     #[display("No secure session established")]
     NoSession,
     #[display("Parsing L3 response failed: {_0}")]
     ParsingError(ParsingError),
+    #[display("Chip did not reboot into the requested mode")]
+    RebootFailed,
     #[display("Request exceeded allowed max size")]
     RequestExceedsSize,
+    /// Returned by [Tropic01::session_start] when authenticated sessions are
+    /// required but no [PinSet] is configured, see
+    /// [Tropic01::with_authenticated_sessions]
+    #[display("Secure session can not be authenticated without pinned certificates")]
+    UnauthenticatedSession,
     #[display("Insufficient user access privileges")]
     Unauthorized,
-    #[display("Chip returned unexpected response status")]
-    UnexpectedResponseStatus,
+    #[display("Chip returned unexpected response status: {_0}")]
+    UnexpectedResponseStatus(#[error(not(source))] L2ResponseContext),
+}
+
+impl<ESpi, EGpio> Error<ESpi, EGpio> {
+    /// Convert the SPI error, keeping all other variants as they are.
+    ///
+    /// This lets transports wrap the driver error in their own error type
+    /// without matching on every variant.
+    pub fn map_bus_error<E>(self, f: impl FnOnce(ESpi) -> E) -> Error<E, EGpio> {
+        self.map_errors(f, |err| err)
+    }
+
+    /// Convert the GPIO error, keeping all other variants as they are.
+    pub fn map_gpio_error<E>(self, f: impl FnOnce(EGpio) -> E) -> Error<ESpi, E> {
+        self.map_errors(|err| err, f)
+    }
+
+    /// Convert both the SPI and the GPIO error, keeping all other variants as
+    /// they are.
+    pub fn map_errors<E1, E2>(
+        self,
+        spi: impl FnOnce(ESpi) -> E1,
+        gpio: impl FnOnce(EGpio) -> E2,
+    ) -> Error<E1, E2> {
+        match self {
+            Self::AlarmMode => Error::AlarmMode,
+            Self::BusError(err) => Error::BusError(spi(err)),
+            Self::CertificatePinMismatch => Error::CertificatePinMismatch,
+            Self::ChipBusy => Error::ChipBusy,
+            Self::Decryption(err) => Error::Decryption(err),
+            Self::Encryption(err) => Error::Encryption(err),
+            Self::GPIOError(err) => Error::GPIOError(gpio(err)),
+            Self::HandshakeFailed => Error::HandshakeFailed,
+            Self::IdentityMismatch => Error::IdentityMismatch,
+            Self::InvalidChipStatus(err) => Error::InvalidChipStatus(err),
+            Self::InvalidCRC => Error::InvalidCRC,
+            Self::InvalidKey => Error::InvalidKey,
+            Self::InvalidL2Response => Error::InvalidL2Response,
+            Self::InvalidCurve => Error::InvalidCurve,
+            Self::InvalidL3Cmd => Error::InvalidL3Cmd,
+            Self::InvalidPublicKey => Error::InvalidPublicKey,
+            Self::L2ResponseError(err) => Error::L2ResponseError(err),
+            Self::L3CmdFailed => Error::L3CmdFailed,
+            Self::L3ResponseBufferOverflow => Error::L3ResponseBufferOverflow,
+            Self::MalformedCertificate(err) => Error::MalformedCertificate(err),
+            Self::NoSession => Error::NoSession,
+            Self::ParsingError(err) => Error::ParsingError(err),
+            Self::RebootFailed => Error::RebootFailed,
+            Self::RequestExceedsSize => Error::RequestExceedsSize,
+            Self::UnauthenticatedSession => Error::UnauthenticatedSession,
+            Self::Unauthorized => Error::Unauthorized,
+            Self::UnexpectedResponseStatus(err) => Error::UnexpectedResponseStatus(err),
+        }
+    }
 }
 
 impl<ESpi, EGpio> From<ParsingError> for Error<ESpi, EGpio> {
@@ -259,10 +649,12 @@ impl Nonce {
         }
     }
 }
-impl AsRef<[u8]> for Nonce {
-    fn as_ref(&self) -> &[u8] {
-        let bytes = self.0.as_bytes();
-        &bytes[..12]
+impl Nonce {
+    /// Return the 96 bits of the nonce in memory order.
+    fn to_bytes(&self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes.copy_from_slice(&self.0.to_ne_bytes()[..12]);
+        bytes
     }
 }
 
@@ -285,7 +677,23 @@ impl Session {
 
 #[cfg(test)]
 mod test {
+    use aes_gcm::aead::arrayvec::ArrayVec;
+    use dummy_pin::DummyPin;
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::ErrorKind;
+    use embedded_hal::digital::ErrorType;
+    use embedded_hal::digital::OutputPin;
+    use x25519_dalek::PublicKey;
+    use x25519_dalek::StaticSecret;
+
+    use crate::Error;
     use crate::Nonce;
+    use crate::POWER_OFF_TIME_NS;
+    use crate::POWER_UP_TIME_NS;
+    use crate::PinError;
+    use crate::SessionKeys;
+    use crate::X25519Dalek;
+    use crate::mock::MockChip;
 
     #[test]
     fn increment_nonce_works() {
@@ -299,4 +707,79 @@ mod test {
         expected = 257;
         assert_eq!(nonce.0, expected);
     }
+
+    #[test]
+    fn power_cycle_works() {
+        struct RecordingDelay(ArrayVec<u32, 2>);
+        impl DelayNs for RecordingDelay {
+            fn delay_ns(&mut self, ns: u32) {
+                self.0.push(ns);
+            }
+        }
+
+        let mut tropic01 = MockChip::session(|_, _| ());
+        let mut delay = RecordingDelay(ArrayVec::new());
+        tropic01
+            .power_cycle(&mut DummyPin::new_low(), &mut delay)
+            .unwrap();
+        assert_eq!(delay.0[..], [POWER_OFF_TIME_NS, POWER_UP_TIME_NS]);
+        assert!(tropic01.session.is_none());
+    }
+
+    #[test]
+    fn recover_from_alarm_power_error_works() {
+        struct NoDelay;
+        impl DelayNs for NoDelay {
+            fn delay_ns(&mut self, _ns: u32) {}
+        }
+        struct FailingPin;
+        impl ErrorType for FailingPin {
+            type Error = ErrorKind;
+        }
+        impl OutputPin for FailingPin {
+            fn set_low(&mut self) -> Result<(), ErrorKind> {
+                Err(ErrorKind::Other)
+            }
+            fn set_high(&mut self) -> Result<(), ErrorKind> {
+                Err(ErrorKind::Other)
+            }
+        }
+
+        let secret = StaticSecret::from([0x01; 32]);
+        let keys = SessionKeys::<X25519Dalek> {
+            shipub: PublicKey::from(&secret),
+            shipriv: secret.clone(),
+            ehpub: PublicKey::from(&secret),
+            ehpriv: secret,
+            pkey_index: 0,
+        };
+        // The driver's chip select is a DummyPin, the power pin has its own error type.
+        let mut tropic01 = MockChip::session(|_, _| ());
+        let err = tropic01
+            .recover_from_alarm(&mut FailingPin, &mut NoDelay, &X25519Dalek, keys)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::GPIOError(PinError::Power(ErrorKind::Other))
+        ));
+    }
+
+    #[test]
+    fn map_error_works() {
+        let err: Error<u8, u16> = Error::BusError(1);
+        assert!(matches!(
+            err.map_bus_error(u32::from),
+            Error::<u32, u16>::BusError(1)
+        ));
+        let err: Error<u8, u16> = Error::GPIOError(2);
+        assert!(matches!(
+            err.map_gpio_error(u32::from),
+            Error::<u8, u32>::GPIOError(2)
+        ));
+        let err: Error<u8, u16> = Error::ChipBusy;
+        assert!(matches!(
+            err.map_errors(u32::from, u32::from),
+            Error::<u32, u32>::ChipBusy
+        ));
+    }
 }
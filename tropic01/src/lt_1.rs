@@ -7,50 +7,81 @@ use packed_struct::PackedStruct as _;
 
 use super::Error;
 use crate::ChipStatus;
-use crate::L1_READ_MAX_TRIES;
 use crate::L2_CMD_REQ_LEN;
+use crate::retry::BusyPoll;
 
 const L2_CMD_ID_GET_RESPONSE: u8 = 0xaa;
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(polls = tracing::field::Empty))
+)]
 pub(super) fn l1_read<SPI: SpiDevice, CS: OutputPin>(
     l2_buf: &mut [u8],
+    busy_poll: BusyPoll,
     spi: &mut SPI,
     cs: &mut Option<CS>,
 ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
-    for _ in 0..L1_READ_MAX_TRIES {
+    for poll in 1..=busy_poll.max_tries() {
+        record!("polls", poll);
         l2_buf.fill(0);
-        l2_buf[0] = L2_CMD_ID_GET_RESPONSE;
-        l2_buf[1] = L2_CMD_REQ_LEN as u8;
+        l2_buf
+            .get_mut(..2)
+            .ok_or(Error::RequestExceedsSize)?
+            .copy_from_slice(&[L2_CMD_ID_GET_RESPONSE, L2_CMD_REQ_LEN as u8]);
         l1_transfer(l2_buf, spi, cs)?;
+        let [chip_status, response_status, ..] = *l2_buf else {
+            return Err(Error::InvalidL2Response);
+        };
 
-        match ChipStatus::unpack(&[l2_buf[0]]) {
-            Ok(status) if status.alarm => return Err(Error::AlarmMode),
+        match ChipStatus::unpack(&[chip_status]) {
+            Ok(status) if status.alarm => {
+                warn!("Chip is in alarm mode");
+                return Err(Error::AlarmMode);
+            },
             // chip status is ready and response status is not `NO_RESP` (0xff)
-            Ok(status) if status.ready && l2_buf[1] != 0xff => {
+            Ok(status) if status.ready && response_status != 0xff => {
                 return Ok(());
             },
-            Ok(_) => l1_delay_ns(spi, cs, 25_000_000)?,
+            Ok(_) => {
+                trace!("Chip not ready, retrying read");
+                l1_delay_ns(spi, cs, busy_poll.delay_ns())?;
+            },
             Err(err) => return Err(Error::InvalidChipStatus(err)),
         }
     }
 
+    warn!("Chip busy after {} read attempts", busy_poll.max_tries());
     Err(Error::ChipBusy)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(polls = tracing::field::Empty))
+)]
 pub(super) fn l1_write<SPI: SpiDevice, CS: OutputPin>(
     l2_buf: &mut [u8],
+    busy_poll: BusyPoll,
     spi: &mut SPI,
     cs: &mut Option<CS>,
 ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
-    for _ in 0..L1_READ_MAX_TRIES {
+    for poll in 1..=busy_poll.max_tries() {
+        record!("polls", poll);
         l1_transfer(l2_buf, spi, cs)?;
+        let chip_status = *l2_buf.first().ok_or(Error::InvalidL2Response)?;
 
-        match ChipStatus::unpack(&[l2_buf[0]]) {
-            Ok(status) if status.alarm => return Err(Error::AlarmMode),
+        match ChipStatus::unpack(&[chip_status]) {
+            Ok(status) if status.alarm => {
+                warn!("Chip is in alarm mode");
+                return Err(Error::AlarmMode);
+            },
             Ok(status) if status.ready => {
                 return Ok(());
             },
-            Ok(_) => l1_delay_ns(spi, cs, 25_000_000)?,
+            Ok(_) => {
+                trace!("Chip not ready, retrying write");
+                l1_delay_ns(spi, cs, busy_poll.delay_ns())?;
+            },
             Err(err) => return Err(Error::InvalidChipStatus(err)),
         }
     }
@@ -58,6 +89,41 @@ pub(super) fn l1_write<SPI: SpiDevice, CS: OutputPin>(
     Ok(())
 }
 
+/// Poll the chip status until the chip is ready.
+///
+/// Only the status byte is read, pending responses are left untouched.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(polls = tracing::field::Empty))
+)]
+pub(super) fn l1_wait_ready<SPI: SpiDevice, CS: OutputPin>(
+    busy_poll: BusyPoll,
+    spi: &mut SPI,
+    cs: &mut Option<CS>,
+) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+    for poll in 1..=busy_poll.max_tries() {
+        record!("polls", poll);
+        let mut buf = [L2_CMD_ID_GET_RESPONSE];
+        l1_transfer(&mut buf, spi, cs)?;
+
+        match ChipStatus::unpack(&buf) {
+            Ok(status) if status.alarm => {
+                warn!("Chip is in alarm mode");
+                return Err(Error::AlarmMode);
+            },
+            Ok(status) if status.ready => return Ok(()),
+            Ok(_) => {
+                trace!("Chip not ready, polling again");
+                l1_delay_ns(spi, cs, busy_poll.delay_ns())?;
+            },
+            Err(err) => return Err(Error::InvalidChipStatus(err)),
+        }
+    }
+
+    warn!("Chip busy after {} polls", busy_poll.max_tries());
+    Err(Error::ChipBusy)
+}
+
 /// Delay for `ns` nanoseconds.
 pub(super) fn l1_delay_ns<SPI: SpiDevice, CS: OutputPin>(
     spi: &mut SPI,
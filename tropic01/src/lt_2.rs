@@ -1,11 +1,15 @@
+use core::fmt;
 use core::iter::repeat_n;
 
+use aes_gcm::aead::arrayvec::ArrayString;
 use aes_gcm::aead::arrayvec::ArrayVec;
 use embedded_hal::digital::ErrorType as GpioErrorType;
 use embedded_hal::digital::OutputPin;
 use embedded_hal::spi::ErrorType as SpiErrorType;
 use embedded_hal::spi::SpiDevice;
 use nom_derive::Nom;
+use sha2::Digest as _;
+use sha2::Sha256;
 use zerocopy::BE;
 use zerocopy::IntoBytes;
 use zerocopy::U16;
@@ -14,6 +18,8 @@ use zerocopy::Unaligned;
 use super::Error;
 use super::Tropic01;
 use crate::Aes256GcmKey;
+use crate::CertIdentity;
+use crate::Config;
 use crate::FromBytes;
 use crate::L2_CHUNK_MAX_DATA_SIZE;
 use crate::L2_CMD_REQ_LEN;
@@ -23,19 +29,33 @@ use crate::L3_FRAME_MAX_SIZE;
 use crate::L3_RES_SIZE_SIZE;
 use crate::L3_TAG_SIZE;
 use crate::Nonce;
+use crate::REBOOT_TIME_NS;
+use crate::TransportStats;
+use crate::chip_id::ChipId;
+use crate::chip_id::SerialNumber;
 use crate::crc16::Crc16;
 use crate::crypto::CryptoError;
 use crate::crypto::X25519;
 use crate::crypto::aesgcm_decrypt;
 use crate::crypto::hkdf;
 use crate::crypto::sha256_sequence;
+use crate::der;
+use crate::der::DerError;
 use crate::lt_1::l1_delay_ns;
 use crate::lt_1::l1_read;
+use crate::lt_1::l1_wait_ready;
 use crate::lt_1::l1_write;
 use crate::lt_3::EncryptedL3CommandPacket;
 use crate::lt_3::L3ResultPacket;
+use crate::pinning::Fingerprint;
 
 const L2_GET_INFO_REQ_CERT_SIZE: usize = 512;
+/// Number of certificate bytes returned by one get_info request
+const L2_GET_INFO_REQ_CERT_BLOCK_SIZE: usize = 128;
+/// DER encoded object identifier of the X25519 algorithm (1.3.101.110)
+const OID_X25519: &[u8] = &[0x2b, 0x65, 0x6e];
+/// DER encoded object identifier of the `serialNumber` attribute (2.5.4.5)
+const OID_SERIAL_NUMBER: &[u8] = &[0x55, 0x04, 0x05];
 /// Protocol Name
 /// See section 7.4.1 of the datasheet, section `Protocol Name`.
 const PROTOCOL_NAME: &[u8; 32] = b"Noise_KK1_25519_AESGCM_SHA256\x00\x00\x00";
@@ -54,6 +74,7 @@ enum L2RequestId {
 
 /// Represents all possible response status codes the chip may return.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Nom, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum ResponseStatus {
     ReqOk = 0x01,
@@ -85,6 +106,35 @@ pub enum ResponseStatus {
     #[display("No L2 response frame available")]
     NoResp = 0xff,
 }
+
+/// Details of an L2 response frame, attached to errors caused by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[display("{status} (request {req_id:#04x}, {len} data bytes)")]
+pub struct L2ResponseContext {
+    req_id: u8,
+    status: ResponseStatus,
+    len: u8,
+}
+
+impl L2ResponseContext {
+    /// ID of the L2 request in flight
+    #[must_use]
+    pub const fn req_id(&self) -> u8 {
+        self.req_id
+    }
+
+    #[must_use]
+    pub const fn status(&self) -> ResponseStatus {
+        self.status
+    }
+
+    /// Length of the response data
+    #[must_use]
+    pub const fn data_len(&self) -> u8 {
+        self.len
+    }
+}
 #[derive(Clone, Debug, IntoBytes, Unaligned)]
 #[repr(C)]
 pub(super) struct L2RequestFrame<'a> {
@@ -95,12 +145,37 @@ pub(super) struct L2RequestFrame<'a> {
 }
 
 impl<'a> L2RequestFrame<'a> {
-    pub fn new(id: u8, data: &'a [&'a [u8]]) -> Self {
-        assert!(data.len() <= u8::MAX as usize);
-        let len = data.iter().map(|d| d.len()).sum::<usize>() as u8;
+    /// Fails with [Error::RequestExceedsSize] if `data` does not fit into a
+    /// single frame.
+    pub fn new<ESpi, EGpio>(id: u8, data: &'a [&'a [u8]]) -> Result<Self, Error<ESpi, EGpio>> {
+        let len = data.iter().map(|d| d.len()).sum::<usize>();
+        if len > L2_CHUNK_MAX_DATA_SIZE {
+            return Err(Error::RequestExceedsSize);
+        }
+        let len = u8::try_from(len).map_err(|_| Error::RequestExceedsSize)?;
 
         let crc = Self::crc(id, len, data);
-        Self { id, len, data, crc }
+        Ok(Self { id, len, data, crc })
+    }
+
+    /// Encode the frame into the beginning of `buf`, returning the number of
+    /// bytes written.
+    ///
+    /// Fails with [Error::RequestExceedsSize] if the frame does not fit into
+    /// `buf`.
+    fn write_to<ESpi, EGpio>(&self, buf: &mut [u8]) -> Result<usize, Error<ESpi, EGpio>> {
+        let mut last_n = 0;
+        for data in [&[self.id, self.len][..]]
+            .into_iter()
+            .chain(self.data.iter().copied())
+            .chain([self.crc.as_bytes()])
+        {
+            buf.get_mut(last_n..last_n + data.len())
+                .ok_or(Error::RequestExceedsSize)?
+                .copy_from_slice(data);
+            last_n += data.len();
+        }
+        Ok(last_n)
     }
 
     fn crc(id: u8, len: u8, data: &'a [&'a [u8]]) -> U16<BE> {
@@ -114,8 +189,9 @@ impl<'a> L2RequestFrame<'a> {
     }
 }
 
+/// Public for fuzzing only, the driver API does not expose L2 frames.
 #[derive(Debug, Nom)]
-struct L2ResponseFrame<'a> {
+pub struct L2ResponseFrame<'a> {
     _chip_status: u8,
     resp_status: ResponseStatus,
     len: u8,
@@ -126,10 +202,20 @@ struct L2ResponseFrame<'a> {
 }
 
 impl<'a> L2ResponseFrame<'a> {
+    #[must_use]
     pub const fn resp_data(&self) -> &'a [u8] {
         self.resp_data
     }
 
+    const fn context(&self, req_id: u8) -> L2ResponseContext {
+        L2ResponseContext {
+            req_id,
+            status: self.resp_status,
+            len: self.len,
+        }
+    }
+
+    #[must_use]
     pub fn check_frame(&self) -> bool {
         let mut crc16 = Crc16::new();
         crc16.update(&[self.resp_status as u8]);
@@ -139,22 +225,100 @@ impl<'a> L2ResponseFrame<'a> {
     }
 }
 
+#[cfg(fuzzing)]
+impl<'a> L2ResponseFrame<'a> {
+    /// Parse a response frame as read from the chip, without checking its
+    /// CRC.
+    pub fn from_bytes(slice: &'a [u8]) -> Result<Self, crate::ParsingError> {
+        <Self as FromBytes<'a>>::from_bytes(slice)
+    }
+}
+
 #[derive(Debug)]
 #[repr(u8)]
 enum InfoReq {
     X509Certificate = 0x00,
     ChipId = 0x01,
-    _RiscvFwVersion = 0x02,
-    _SpectFwVersion = 0x04,
+    RiscvFwVersion = 0x02,
+    SpectFwVersion = 0x04,
     _FwBank = 0xb0,
 }
 
+/// Represents all errors that can happen while extracting the public key from
+/// the chip's [X509Certificate].
 #[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum PublicKeyError {
+    #[display("Malformed X509 certificate: {_0}")]
+    MalformedCertificate(DerError),
     #[display("Could not find public key in X509 certificate")]
     PublicKeyNotFound,
+    #[display("X509 certificate does not contain a X25519 public key")]
+    UnsupportedAlgorithm,
+}
+
+impl From<DerError> for PublicKeyError {
+    fn from(other: DerError) -> Self {
+        Self::MalformedCertificate(other)
+    }
+}
+
+/// Represents all errors that can happen while PEM encoding the chip's
+/// [X509Certificate].
+#[derive(Debug, derive_more::Display, derive_more::Error, derive_more::From)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum PemError {
+    #[display("Malformed X509 certificate: {_0}")]
+    MalformedCertificate(DerError),
+    #[display("Writing PEM output failed")]
+    Write(#[cfg_attr(feature = "defmt", defmt(Debug2Format))] fmt::Error),
+}
+
+/// Base64 encode up to three bytes, padding the output if needed.
+pub(crate) fn base64_chunk(chunk: &[u8]) -> ArrayString<4> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut bytes = [0; 4];
+    for (byte, input) in bytes[1..].iter_mut().zip(chunk) {
+        *byte = *input;
+    }
+    let n = u32::from_be_bytes(bytes);
+
+    let mut out = ArrayString::new();
+    for i in 0..4 {
+        if i <= chunk.len() {
+            // Masked to 6 bits, so the alphabet always has the character.
+            let idx = (n >> (18 - 6 * i)) & 0x3f;
+            if let Some(c) = ALPHABET.get(idx as usize) {
+                out.push(char::from(*c));
+            }
+        } else {
+            out.push('=');
+        }
+    }
+    out
+}
+
+/// Return the number of certificate bytes to read from the chip, given the
+/// first block.
+///
+/// If the block starts with the DER header of the certificate, only the
+/// blocks covering the certificate are needed. Anything else, e.g. the header
+/// of a certificate store, means reading all [L2_GET_INFO_REQ_CERT_SIZE]
+/// bytes.
+fn cert_read_len(block: &[u8]) -> usize {
+    der::encoded_len(block)
+        .unwrap_or(L2_GET_INFO_REQ_CERT_SIZE)
+        .min(L2_GET_INFO_REQ_CERT_SIZE)
 }
 
+/// Version byte at the start of the certificate store of newer firmware
+const CERT_STORE_VERSION: u8 = 0x01;
+/// Prefix of the X25519 public key in the `subjectPublicKeyInfo`: the end of
+/// the algorithm OID 1.3.101.110 and the header of the BIT STRING
+const LEGACY_PUBLIC_KEY_PREFIX: [u8; 4] = [0x65, 0x6e, 0x03, 0x21];
+
 /// The x509 certificate of the chip containing the public key.
 #[derive(Debug)]
 pub struct X509Certificate<'a> {
@@ -162,37 +326,374 @@ pub struct X509Certificate<'a> {
 }
 
 impl<'a> X509Certificate<'a> {
-    const fn new(data: &'a [u8; L2_GET_INFO_REQ_CERT_SIZE]) -> Self {
+    /// Number of bytes read from the chip
+    pub const SIZE: usize = L2_GET_INFO_REQ_CERT_SIZE;
+
+    /// Wrap the bytes read from the chip, see [Tropic01::get_info_cert].
+    ///
+    /// Depending on the firmware, `data` starts either with the device
+    /// certificate or with the header of a certificate store, which is
+    /// followed by the device certificate.
+    #[must_use]
+    pub const fn new(data: &'a [u8; L2_GET_INFO_REQ_CERT_SIZE]) -> Self {
         Self { data }
     }
 
-    /// Return the public key
-    pub fn public_key(&self) -> Result<&[u8; 32], PublicKeyError> {
-        // TODO consider using appropriate ASN.1 DER parsing for this
-        let seq = [0x65, 0x6e, 0x03, 0x21];
-        let len = seq.len();
-        let pos = self
-            .data
-            .windows(len)
-            .position(|window| window == seq)
-            .ok_or(PublicKeyError::PublicKeyNotFound)?;
-        let start = pos + len + 1; // +1 to remove leading '0' for uncompressed public key
-        self.data[start..start + 32]
-            .try_into()
-            .map_err(|_| PublicKeyError::PublicKeyNotFound)
+    /// Return the DER encoding of the device certificate.
+    ///
+    /// The chip always returns [L2_GET_INFO_REQ_CERT_SIZE] bytes, this strips
+    /// the store header and anything after the end of the certificate.
+    pub fn as_der(&self) -> Result<&'a [u8], DerError> {
+        der::first_item(self.certificate()?)
     }
+
+    /// Write the certificate PEM encoded to `w`.
+    pub fn write_pem<W: fmt::Write>(&self, w: &mut W) -> Result<(), PemError> {
+        let der = self.as_der()?;
+        w.write_str("-----BEGIN CERTIFICATE-----\n")?;
+        // 48 bytes of input make up one 64 character line
+        for line in der.chunks(48) {
+            for chunk in line.chunks(3) {
+                w.write_str(base64_chunk(chunk).as_str())?;
+            }
+            w.write_char('\n')?;
+        }
+        w.write_str("-----END CERTIFICATE-----\n")?;
+        Ok(())
+    }
+
+    /// Return the SHA-256 fingerprint of the DER encoded certificate.
+    pub fn fingerprint(&self) -> Result<Fingerprint, DerError> {
+        Ok(Sha256::digest(self.as_der()?).into())
+    }
+
+    /// Return the chip's static X25519 public key (`STPUB`).
+    ///
+    /// The key is read from the `subjectPublicKeyInfo` of the certificate,
+    /// which uses the X25519 algorithm identifier (OID 1.3.101.110) that
+    /// most X509 parsers do not recognize.
+    ///
+    /// If the certificate can not be parsed, the key is searched for by the
+    /// bytes preceding it, as earlier versions of the driver did. Only the
+    /// device certificate is searched, i.e. [Self::as_der] or, if the
+    /// certificate runs past the bytes read from the chip, everything from
+    /// its start. Certificates with other keys fail with
+    /// [PublicKeyError::UnsupportedAlgorithm] and are not searched.
+    pub fn public_key(&self) -> Result<&'a [u8; 32], PublicKeyError> {
+        match self.parse_public_key() {
+            Err(PublicKeyError::MalformedCertificate(err)) => self
+                .search_public_key()
+                .ok_or(PublicKeyError::MalformedCertificate(err)),
+            result => result,
+        }
+    }
+
+    fn parse_public_key(&self) -> Result<&'a [u8; 32], PublicKeyError> {
+        let mut spki = der::Reader::new(self.tbs()?.subject_public_key_info);
+        let mut algorithm = der::Reader::new(spki.read(der::TAG_SEQUENCE)?);
+        if algorithm.read(der::TAG_OID)? != OID_X25519 {
+            return Err(PublicKeyError::UnsupportedAlgorithm);
+        }
+        match spki.read(der::TAG_BIT_STRING)? {
+            // The leading byte holds the number of unused bits, which is 0 for keys.
+            [0, key @ ..] => key
+                .try_into()
+                .map_err(|_| PublicKeyError::PublicKeyNotFound),
+            _ => Err(PublicKeyError::PublicKeyNotFound),
+        }
+    }
+
+    /// Return the content of the certificate's `serialNumber` field.
+    pub fn serial_number(&self) -> Result<&'a [u8], DerError> {
+        Ok(self.tbs()?.serial_number)
+    }
+
+    /// Return the DER encoded content of the certificate's `subject` field.
+    pub fn subject(&self) -> Result<&'a [u8], DerError> {
+        Ok(self.tbs()?.subject)
+    }
+
+    /// Return an iterator over the certificate's extensions.
+    ///
+    /// The iterator is empty if the certificate has no extensions.
+    pub fn extensions(&self) -> Result<Extensions<'a>, DerError> {
+        Ok(Extensions {
+            reader: der::Reader::new(self.tbs()?.extensions),
+        })
+    }
+
+    /// Return the extension with the DER encoded object identifier `oid`,
+    /// e.g. `[0x55, 0x1d, 0x13]` for basic constraints (2.5.29.19).
+    pub fn extension(&self, oid: &[u8]) -> Result<Option<Extension<'a>>, DerError> {
+        for extension in self.extensions()? {
+            let extension = extension?;
+            if extension.oid == oid {
+                return Ok(Some(extension));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return the value of the first attribute with the DER encoded object
+    /// identifier `oid` in the certificate's subject, e.g. `[0x55, 0x04,
+    /// 0x03]` for the common name (2.5.4.3).
+    pub fn subject_attribute(&self, oid: &[u8]) -> Result<Option<&'a [u8]>, DerError> {
+        let mut subject = der::Reader::new(self.subject()?);
+        while subject.peek_tag().is_some() {
+            let mut rdn = der::Reader::new(subject.read(der::TAG_SET)?);
+            while rdn.peek_tag().is_some() {
+                let mut attribute = der::Reader::new(rdn.read(der::TAG_SEQUENCE)?);
+                if attribute.read(der::TAG_OID)? == oid {
+                    return Ok(Some(attribute.read_any()?.value));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return the chip serial number (`SER_NUM`) the certificate was issued
+    /// for, see [Tropic01::verify_identity].
+    fn chip_serial_number(&self) -> Result<Option<[u8; SerialNumber::SIZE]>, DerError> {
+        if let Some(value) = self.subject_attribute(OID_SERIAL_NUMBER)? {
+            return Ok(decode_hex(value));
+        }
+        let serial_number = match self.serial_number()? {
+            // Leading zero keeping the INTEGER positive
+            [0, rest @ ..] if rest.len() == SerialNumber::SIZE => rest,
+            serial_number => serial_number,
+        };
+        Ok(serial_number.try_into().ok())
+    }
+
+    fn search_public_key(&self) -> Option<&'a [u8; 32]> {
+        // Neither the store header nor the bytes after the certificate are
+        // covered by its fingerprint.
+        let cert = self.as_der().or_else(|_| self.certificate()).ok()?;
+        let pos = cert
+            .windows(LEGACY_PUBLIC_KEY_PREFIX.len())
+            .position(|w| w == LEGACY_PUBLIC_KEY_PREFIX)?;
+        // Skip the unused bits byte of the BIT STRING.
+        let start = pos + LEGACY_PUBLIC_KEY_PREFIX.len() + 1;
+        cert.get(start..start + 32)?.try_into().ok()
+    }
+
+    /// Return the data starting at the device certificate.
+    ///
+    /// The certificate store starts with the version, the number of
+    /// certificates and their lengths as big endian `u16`, followed by the
+    /// certificates with the device certificate first.
+    fn certificate(&self) -> Result<&'a [u8], DerError> {
+        match *self.data {
+            [der::TAG_SEQUENCE, ..] => Ok(self.data),
+            [CERT_STORE_VERSION, count @ 1..=u8::MAX, ..] => self
+                .data
+                .get(2 + 2 * usize::from(count)..)
+                .ok_or(DerError::Truncated),
+            [tag, ..] => Err(DerError::UnexpectedTag(tag)),
+        }
+    }
+
+    fn tbs(&self) -> Result<TbsCertificate<'a>, DerError> {
+        let mut cert = der::Reader::new(self.certificate()?);
+        let mut cert = der::Reader::new(cert.read(der::TAG_SEQUENCE)?);
+        let mut tbs = der::Reader::new(cert.read(der::TAG_SEQUENCE)?);
+        // version
+        tbs.skip_optional(der::TAG_CONTEXT_0)?;
+        let serial_number = tbs.read(der::TAG_INTEGER)?;
+        // signature, issuer, validity
+        for _ in 0..3 {
+            tbs.read(der::TAG_SEQUENCE)?;
+        }
+        let subject = tbs.read(der::TAG_SEQUENCE)?;
+        let subject_public_key_info = tbs.read(der::TAG_SEQUENCE)?;
+        // issuerUniqueID, subjectUniqueID
+        tbs.skip_optional(der::TAG_IMPLICIT_1)?;
+        tbs.skip_optional(der::TAG_IMPLICIT_2)?;
+        let extensions = match tbs.read_optional(der::TAG_CONTEXT_3)? {
+            Some(extensions) => der::Reader::new(extensions).read(der::TAG_SEQUENCE)?,
+            None => &[],
+        };
+        Ok(TbsCertificate {
+            serial_number,
+            subject,
+            subject_public_key_info,
+            extensions,
+        })
+    }
+
+    /// Return the chip's static X25519 public key (`STPUB`) as the public key
+    /// type of a [X25519] implementation.
+    pub fn stpub<P: From<[u8; 32]>>(&self) -> Result<P, PublicKeyError> {
+        self.public_key().map(|key| P::from(*key))
+    }
+}
+
+/// Decode `hex` into `N` bytes, accepting both upper and lower case digits.
+fn decode_hex<const N: usize>(hex: &[u8]) -> Option<[u8; N]> {
+    if hex.len() != 2 * N || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (byte, digits) in bytes.iter_mut().zip(hex.chunks_exact(2)) {
+        *byte = u8::from_str_radix(core::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Fields of the `tbsCertificate` used by the driver
+struct TbsCertificate<'a> {
+    serial_number: &'a [u8],
+    subject: &'a [u8],
+    subject_public_key_info: &'a [u8],
+    extensions: &'a [u8],
+}
+
+/// An extension of an [X509Certificate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Extension<'a> {
+    oid: &'a [u8],
+    critical: bool,
+    value: &'a [u8],
+}
+
+impl<'a> Extension<'a> {
+    /// Return the DER encoded object identifier (`extnID`) of the extension.
+    #[must_use]
+    pub const fn oid(&self) -> &'a [u8] {
+        self.oid
+    }
+
+    #[must_use]
+    pub const fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Return the content of the `extnValue` octet string, i.e. the DER
+    /// encoding of the extension's value.
+    #[must_use]
+    pub const fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+/// Iterator over the extensions of an [X509Certificate], see
+/// [X509Certificate::extensions].
+///
+/// Iteration stops after the first malformed extension.
+#[derive(Debug, Clone)]
+pub struct Extensions<'a> {
+    reader: der::Reader<'a>,
+}
+
+impl<'a> Iterator for Extensions<'a> {
+    type Item = Result<Extension<'a>, DerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.peek_tag()?;
+        let extension = parse_extension(&mut self.reader);
+        if extension.is_err() {
+            self.reader = der::Reader::new(&[]);
+        }
+        Some(extension)
+    }
+}
+
+fn parse_extension<'a>(reader: &mut der::Reader<'a>) -> Result<Extension<'a>, DerError> {
+    let mut extension = der::Reader::new(reader.read(der::TAG_SEQUENCE)?);
+    let oid = extension.read(der::TAG_OID)?;
+    // critical defaults to FALSE and is omitted in that case
+    let critical = extension
+        .read_optional(der::TAG_BOOLEAN)?
+        .is_some_and(|value| value != [0]);
+    let value = extension.read(der::TAG_OCTET_STRING)?;
+    Ok(Extension {
+        oid,
+        critical,
+        value,
+    })
 }
 
 /// Represents the types of startup requests the chip supports.
-#[derive(Debug)]
+///
+/// [StartupReq::MaintenanceReboot] starts the bootloader instead of the
+/// application firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum StartupReq {
     Reboot = 0x01,
     MaintenanceReboot = 0x03,
 }
 
+/// Version of a firmware running on the chip.
+///
+/// The chip reports versions as 4 bytes, least significant (build) byte
+/// first. The most significant bit is set for bootloader versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FirmwareVersion([u8; 4]);
+
+impl FirmwareVersion {
+    const BOOTLOADER_FLAG: u8 = 0x80;
+
+    #[must_use]
+    pub const fn new(bytes: [u8; 4]) -> Self {
+        Self(bytes)
+    }
+
+    #[must_use]
+    pub const fn major(&self) -> u8 {
+        self.0[3] & !Self::BOOTLOADER_FLAG
+    }
+
+    #[must_use]
+    pub const fn minor(&self) -> u8 {
+        self.0[2]
+    }
+
+    #[must_use]
+    pub const fn patch(&self) -> u8 {
+        self.0[1]
+    }
+
+    #[must_use]
+    pub const fn build(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Check whether this is the version of the bootloader, i.e. the chip
+    /// runs in maintenance mode.
+    #[must_use]
+    pub const fn is_bootloader(&self) -> bool {
+        self.0[3] & Self::BOOTLOADER_FLAG != 0
+    }
+
+    #[must_use]
+    pub const fn to_bytes(&self) -> [u8; 4] {
+        self.0
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}+{}",
+            self.major(),
+            self.minor(),
+            self.patch(),
+            self.build()
+        )
+    }
+}
+
 /// Represents all kinds of sleep requests the chip supports.
-#[derive(Debug)]
+///
+/// The chip wakes up from both modes on SPI traffic, see
+/// [Tropic01::wake].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum SleepReq {
     Sleep = 0x05,
@@ -216,9 +717,28 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         L2ResponseFrame<'_>,
         Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>,
     > {
-        get_info_req(req, block, &mut self.l2_buf, &mut self.spi, &mut self.cs)
+        get_info_req(
+            req,
+            block,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )
     }
 
+    /// Read the chip's X509 certificate.
+    ///
+    /// If the chip returns the bare device certificate, only the blocks
+    /// covering it are requested and the remainder of the returned buffer is
+    /// zeroed. Newer firmware returns a certificate store instead, of which
+    /// the first [L2_GET_INFO_REQ_CERT_SIZE] bytes are read; they start with
+    /// the device certificate.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = tracing::field::Empty))
+    )]
     pub fn get_info_cert(
         &mut self,
     ) -> Result<
@@ -227,70 +747,315 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
     > {
         self.l3_buf.clear();
         self.l3_buf.extend(repeat_n(0, L2_GET_INFO_REQ_CERT_SIZE));
-        for (i, chunk) in self.l3_buf.chunks_mut(128).enumerate() {
+        // Read all blocks unless the certificate turns out to be shorter.
+        let mut cert_len = L2_GET_INFO_REQ_CERT_SIZE;
+        for (i, chunk) in self
+            .l3_buf
+            .chunks_mut(L2_GET_INFO_REQ_CERT_BLOCK_SIZE)
+            .enumerate()
+        {
+            if i * L2_GET_INFO_REQ_CERT_BLOCK_SIZE >= cert_len {
+                break;
+            }
             let res = get_info_req(
                 InfoReq::X509Certificate,
                 i as u8,
+                self.config,
+                &mut self.stats,
                 &mut self.l2_buf,
                 &mut self.spi,
                 &mut self.cs,
             )?;
-            chunk[..res.resp_data.len()].copy_from_slice(res.resp_data);
+            chunk
+                .get_mut(..res.resp_data.len())
+                .ok_or(Error::InvalidL2Response)?
+                .copy_from_slice(res.resp_data);
+            if i == 0 {
+                cert_len = cert_read_len(chunk);
+            }
         }
-        Ok(X509Certificate::new(
-            self.l3_buf
-                .as_slice()
-                .try_into()
-                // Safety: Expect is safe since `l3_buf` has L2_GET_INFO_REQ_CERT_SIZE items
-                .expect("l3 buffer length to match certificate length"),
-        ))
+        record!("len", cert_len);
+        let cert = self
+            .l3_buf
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::InvalidL2Response)?;
+        Ok(X509Certificate::new(cert))
     }
 
+    /// Read the raw chip ID, see [Self::chip_id] for the decoded version.
+    ///
+    /// The returned data is borrowed from the driver's internal buffer.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn get_info_chip_id(
         &mut self,
-    ) -> Result<&[u8], Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+    ) -> Result<
+        &[u8; ChipId::SIZE],
+        Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>,
+    > {
         let res = self.get_info_req(InfoReq::ChipId, 0)?;
-        Ok(res.resp_data())
+        res.resp_data()
+            .try_into()
+            .map_err(|_| Error::InvalidL2Response)
+    }
+
+    /// Read the version of the RISC-V firmware.
+    ///
+    /// In maintenance mode this is the version of the bootloader, see
+    /// [FirmwareVersion::is_bootloader].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn get_info_riscv_fw_ver(
+        &mut self,
+    ) -> Result<FirmwareVersion, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        let res = self.get_info_req(InfoReq::RiscvFwVersion, 0)?;
+        let bytes = res
+            .resp_data()
+            .try_into()
+            .map_err(|_| Error::InvalidL2Response)?;
+        Ok(FirmwareVersion::new(bytes))
+    }
+
+    /// Read the version of the SPECT firmware.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn get_info_spect_fw_ver(
+        &mut self,
+    ) -> Result<FirmwareVersion, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        let res = self.get_info_req(InfoReq::SpectFwVersion, 0)?;
+        let bytes = res
+            .resp_data()
+            .try_into()
+            .map_err(|_| Error::InvalidL2Response)?;
+        Ok(FirmwareVersion::new(bytes))
+    }
+
+    /// Read and decode the chip ID.
+    ///
+    /// The chip ID is only read from the chip on the first call and cached
+    /// afterwards, see [Self::invalidate_cache].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn chip_id(
+        &mut self,
+    ) -> Result<ChipId, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        if let Some(chip_id) = &self.cache.chip_id {
+            return Ok(chip_id.clone());
+        }
+        let res = self.get_info_chip_id()?;
+        let chip_id = ChipId::try_from(&res[..])?;
+        self.cache.chip_id = Some(chip_id.clone());
+        Ok(chip_id)
+    }
+
+    /// Return the chip's static X25519 public key (`STPUB`) from its device
+    /// certificate.
+    ///
+    /// The certificate is only read from the chip on first use and cached
+    /// afterwards, see [Self::invalidate_cache].
+    pub fn stpub(
+        &mut self,
+    ) -> Result<[u8; 32], Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        Ok(self.cert_identity()?.stpub)
     }
 
+    /// Return the SHA-256 fingerprint of the chip's device certificate.
+    ///
+    /// The certificate is only read from the chip on first use and cached
+    /// afterwards, see [Self::invalidate_cache].
+    pub fn cert_fingerprint(
+        &mut self,
+    ) -> Result<Fingerprint, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        self.cert_identity()?
+            .fingerprint
+            .map_err(Error::MalformedCertificate)
+    }
+
+    fn cert_identity(
+        &mut self,
+    ) -> Result<CertIdentity, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        if let Some(identity) = self.cache.cert {
+            return Ok(identity);
+        }
+        let cert = self.get_info_cert()?;
+        let identity = CertIdentity {
+            stpub: *cert.public_key().map_err(|_| Error::InvalidPublicKey)?,
+            fingerprint: cert.fingerprint(),
+            serial_number: cert.chip_serial_number(),
+        };
+        self.cache.cert = Some(identity);
+        Ok(identity)
+    }
+
+    /// Check that the chip ID matches the identity in the chip's certificate.
+    ///
+    /// The serial number (`SER_NUM`) read from the chip ID must equal the
+    /// serial number the certificate was issued for, otherwise
+    /// [Error::IdentityMismatch] is returned. A mismatch indicates that the
+    /// certificate does not belong to the chip, e.g. because the chip was
+    /// swapped or cloned.
+    ///
+    /// The certificate names the chip by the `serialNumber` attribute
+    /// (2.5.4.5) of its subject, hex encoded. Without that attribute the
+    /// certificate's own serial number is compared. Fails with
+    /// [Error::MalformedCertificate] if the certificate can not be parsed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn verify_identity(
+        &mut self,
+    ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        let ser_num = self.chip_id()?.serial_number().to_bytes();
+        let serial_number = self
+            .cert_identity()?
+            .serial_number
+            .map_err(Error::MalformedCertificate)?;
+        match serial_number {
+            Some(serial_number) if serial_number == ser_num => Ok(()),
+            _ => Err(Error::IdentityMismatch),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn get_log_req(
         &mut self,
     ) -> Result<&[u8], Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         // TODO impl chunked response (response can be upto 255 bytes, exceeding normal
         // l2 response)
         let data = [];
-        let frame = L2RequestFrame::new(L2RequestId::GetLog as u8, &data);
-        let res = l2_transfer(frame, &mut self.l2_buf, &mut self.spi, &mut self.cs)?;
+        let frame = L2RequestFrame::new(L2RequestId::GetLog as u8, &data)?;
+        let res = l2_transfer(
+            frame,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )?;
         Ok(res.resp_data())
     }
 
+    /// Put the chip to sleep.
+    ///
+    /// Sleeping ends the secure session, a new one needs to be started after
+    /// [Self::wake].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(req = ?req))
+    )]
     pub fn sleep_req(
         &mut self,
         req: SleepReq,
     ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         let data = [&[req as u8][..]];
-        let frame = L2RequestFrame::new(L2RequestId::SleepReq as u8, &data[..]);
-        l2_transfer(frame, &mut self.l2_buf, &mut self.spi, &mut self.cs)?;
+        let frame = L2RequestFrame::new(L2RequestId::SleepReq as u8, &data[..])?;
+        l2_transfer(
+            frame,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )?;
+        self.session = None;
+        Ok(())
+    }
+
+    /// Wake the chip up after [Self::sleep_req].
+    ///
+    /// The chip wakes up on the falling edge of the chip select line. This
+    /// polls the chip status according to the configured [crate::BusyPoll]
+    /// until the chip is ready again.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn wake(
+        &mut self,
+    ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        l1_wait_ready(self.config.busy_poll, &mut self.spi, &mut self.cs)?;
+        debug!("Chip woke up");
         Ok(())
     }
 
+    /// Reboot the chip.
+    ///
+    /// Rebooting ends the secure session.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(req = ?req))
+    )]
     pub fn startup_req(
         &mut self,
         req: StartupReq,
     ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         let data = [&[req as u8][..]];
-        let frame = L2RequestFrame::new(L2RequestId::StartupReq as u8, &data[..]);
-        l2_transfer(frame, &mut self.l2_buf, &mut self.spi, &mut self.cs)?;
+        let frame = L2RequestFrame::new(L2RequestId::StartupReq as u8, &data[..])?;
+        l2_transfer(
+            frame,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )?;
+        self.session = None;
         Ok(())
     }
 
+    /// Reboot the chip and wait until it is back up.
+    ///
+    /// Unlike [Self::startup_req] this confirms that the chip came up in the
+    /// requested mode, i.e. runs the bootloader after
+    /// [StartupReq::MaintenanceReboot] and the application firmware otherwise,
+    /// and returns the version of the running firmware. Fails with
+    /// [Error::RebootFailed] if the chip runs the wrong firmware.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(mode = ?mode))
+    )]
+    pub fn reboot_into(
+        &mut self,
+        mode: StartupReq,
+    ) -> Result<FirmwareVersion, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        self.startup_req(mode)?;
+        l1_delay_ns(&mut self.spi, &mut self.cs, REBOOT_TIME_NS)?;
+        l1_wait_ready(self.config.busy_poll, &mut self.spi, &mut self.cs)?;
+        let version = self.get_info_riscv_fw_ver()?;
+        if version.is_bootloader() != (mode == StartupReq::MaintenanceReboot) {
+            warn!("Chip did not reboot into the requested mode");
+            return Err(Error::RebootFailed);
+        }
+        debug!("Chip rebooted");
+        Ok(version)
+    }
+
     /// Start a secure session
     ///
+    /// The chip's static public key (`STPUB`) is taken from its certificate,
+    /// see [Self::stpub], and mixed into the handshake. The chip proves
+    /// possession of the matching private key with the authentication tag of
+    /// its handshake response, failing with [Error::HandshakeFailed]
+    /// otherwise.
+    ///
+    /// This alone does not authenticate the chip: the certificate is read
+    /// over the same bus as the handshake and is not verified against a
+    /// certificate authority, so a device in the middle can present its own
+    /// certificate and key. The session is only authenticated if a
+    /// [crate::PinSet] was configured with [Self::with_pin_set], in which
+    /// case the session is only started if the fingerprint of the chip's
+    /// certificate is part of it. Certificates which can not be fingerprinted
+    /// then fail with [Error::MalformedCertificate]. [Self::verify_identity]
+    /// additionally ties the pinned certificate to the chip ID. Use
+    /// [Self::with_authenticated_sessions] to refuse sessions without a
+    /// [crate::PinSet].
+    ///
     /// Arguments:
     /// - shipub: Secret host public key corresponding to slot `pkey_index`
     /// - shipriv: Secret host private key corresponding to slot `pkey_index`
     /// - ehpub: Ephemeral public key
     /// - ehpriv: Ephemeral private key
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(pkey_index))
+    )]
     pub fn session_start<X: X25519>(
         &mut self,
         x25519: &X,
@@ -300,20 +1065,33 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         ehpriv: X::StaticSecret,
         pkey_index: u8,
     ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
-        let cert = self.get_info_cert()?;
-        let stpub = *cert.public_key().map_err(|_| Error::InvalidPublicKey)?;
+        if self.pins.is_none() && self.config.authenticated_sessions {
+            warn!("No pinned certificates to authenticate the chip");
+            return Err(Error::UnauthenticatedSession);
+        }
+        let CertIdentity {
+            stpub, fingerprint, ..
+        } = self.cert_identity()?;
+        // Certificates that can not be fingerprinted are only rejected when pinning.
+        if let Some(pins) = &self.pins {
+            let fingerprint = fingerprint.map_err(Error::MalformedCertificate)?;
+            if !pins.contains(&fingerprint) {
+                warn!("Chip certificate is not pinned");
+                return Err(Error::CertificatePinMismatch);
+            }
+        }
+        debug!("Handshake: chip certificate accepted");
 
         let hdshk = self.handshake_req::<X>(ehpub, 0)?;
+        debug!("Handshake: response received");
         let etpub: [u8; 32] = hdshk
             .etpub
             .try_into()
-            // Safety: This is safe since the field is verified in HandShakeResponse
-            .expect("response to contain public key (32 bytes)");
+            .map_err(|_| Error::InvalidL2Response)?;
         let ttauth: [u8; 16] = hdshk
             .ttauth
             .try_into()
-            // Safety: This is safe since the field is verified in HandShakeResponse
-            .expect("response to contain authentication tag (16 bytes)");
+            .map_err(|_| Error::InvalidL2Response)?;
 
         let (kcmd, kres) = process_handshake(
             x25519,
@@ -326,9 +1104,13 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
             ttauth,
             pkey_index,
         )
-        .map_err(|_| Error::HandshakeFailed)?;
+        .map_err(|_| {
+            warn!("Handshake: chip authentication failed");
+            Error::HandshakeFailed
+        })?;
 
         self.session = Some(super::Session::new(kcmd, kres));
+        debug!("Handshake: secure session established");
 
         Ok(())
     }
@@ -342,8 +1124,15 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>,
     > {
         let data = [ehpub.as_ref(), &[pkey_index][..]];
-        let frame = L2RequestFrame::new(L2RequestId::HandshakeReq as u8, &data[..]);
-        let res = l2_transfer(frame, &mut self.l2_buf, &mut self.spi, &mut self.cs)?;
+        let frame = L2RequestFrame::new(L2RequestId::HandshakeReq as u8, &data[..])?;
+        let res = l2_transfer(
+            frame,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )?;
 
         Ok(HandShakeResponse::from_bytes(res.resp_data)?)
     }
@@ -351,88 +1140,145 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
 
 /// Write req into l2_buf and send to chip, then read result via GetRequest
 /// command.
+///
+/// Failed requests are repeated as configured by the [crate::RetryPolicy] in
+/// `config`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(req_id = req.id, attempts = 1))
+)]
 fn l2_transfer<'a, SPI: SpiDevice, CS: OutputPin>(
     req: L2RequestFrame<'_>,
+    config: Config,
+    stats: &mut TransportStats,
     l2_buf: &'a mut [u8],
     spi: &'a mut SPI,
     cs: &'a mut Option<CS>,
 ) -> Result<L2ResponseFrame<'a>, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
 {
-    l2_transfer_helper(Some(req), l2_buf, spi, cs)
+    if config.command_delay_ns > 0 {
+        l1_delay_ns(spi, cs, config.command_delay_ns)?;
+    }
+    let retry = config.retry;
+    let mut attempt = 1;
+    loop {
+        match l2_transfer_helper(Some(req.clone()), config, stats, l2_buf, spi, cs).map(|_| ()) {
+            Ok(()) => break,
+            Err(err) if attempt < retry.max_attempts() && retry.is_retryable(&err) => {
+                stats.record_error(&err);
+                stats.record_retry();
+                attempt += 1;
+                record!("attempts", attempt);
+                debug!("Retrying L2 request, attempt {}", attempt);
+                l1_delay_ns(spi, cs, retry.backoff_ns())?;
+            },
+            Err(err) => {
+                stats.record_error(&err);
+                return Err(err);
+            },
+        }
+    }
+    Ok(L2ResponseFrame::from_bytes(l2_buf)?)
 }
 
 /// If req is None, the caller needs to fill l2_buf with the request before
 /// calling this.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "trace",
+        skip_all,
+        fields(
+            req_id = req.as_ref().map(|req| req.id),
+            retries = 0,
+        )
+    )
+)]
 fn l2_transfer_helper<'a, SPI: SpiDevice, CS: OutputPin>(
     mut req: Option<L2RequestFrame<'_>>,
+    config: Config,
+    stats: &mut TransportStats,
     l2_buf: &'a mut [u8],
     spi: &'a mut SPI,
     cs: &'a mut Option<CS>,
 ) -> Result<L2ResponseFrame<'a>, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
 {
-    for _ in 0..4 {
+    for retry in 0..4 {
+        record!("retries", retry);
         if let Some(req) = req.as_ref() {
             l2_buf.fill(0);
-            l2_buf[0] = req.id;
-            l2_buf[1] = req.len;
-            let mut last_n = 2;
-            for data in req.data {
-                l2_buf[last_n..last_n + data.len()].copy_from_slice(data);
-                last_n += data.len();
-            }
-            l2_buf[last_n..last_n + 2].copy_from_slice(req.crc.as_bytes());
+            req.write_to(l2_buf)?;
         }
-        l1_write(l2_buf, spi, cs)?;
+        let req_id = *l2_buf.first().ok_or(Error::RequestExceedsSize)?;
+        stats.record_request();
+        l1_write(l2_buf, config.busy_poll, spi, cs)?;
         l2_buf.fill(0);
-        l1_read(l2_buf, spi, cs)?;
+        l1_read(l2_buf, config.busy_poll, spi, cs)?;
         let res = L2ResponseFrame::from_bytes(l2_buf)?;
 
         if !res.check_frame() {
             return Err(Error::InvalidCRC);
         }
 
+        trace!("L2 response status: {}", res.resp_status);
         match res.resp_status {
             ResponseStatus::NoSession => return Err(Error::NoSession),
             ResponseStatus::GenErr => {
+                debug!("L2 generic error, requesting resend");
+                stats.record_resend();
                 // Retry but ask chip to resend the last response frame.
-                req.replace(L2RequestFrame::new(L2RequestId::ResendReq as u8, &[]));
+                req.replace(L2RequestFrame::new(L2RequestId::ResendReq as u8, &[])?);
             },
             ResponseStatus::CrcErr => {
                 // This may happen for commands immediately issued after a reboot of the
                 // chip, in which case the chip will appear ready but
                 // respond with CRC errors. If this happens, wait
                 // and retry by resending the original request.
-                l1_delay_ns(spi, cs, 25_000_000)?;
+                debug!("L2 CRC error, resending request");
+                stats.record_resend();
+                l1_delay_ns(spi, cs, config.busy_poll.delay_ns())?;
             },
             ResponseStatus::ReqOk | ResponseStatus::ReqCont => {
                 return Ok(L2ResponseFrame::from_bytes(l2_buf)?);
             },
             ResponseStatus::ResOk | ResponseStatus::ResCont => {
-                return Err(Error::UnexpectedResponseStatus);
+                return Err(Error::UnexpectedResponseStatus(res.context(req_id)));
             },
-            err => return Err(Error::L2ResponseError(err)),
+            _ => return Err(Error::L2ResponseError(res.context(req_id))),
         }
     }
     Err(Error::InvalidL2Response)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "trace",
+        skip_all,
+        fields(
+            len = usize::from(req.cmd_size()),
+            chunks = tracing::field::Empty,
+        )
+    )
+)]
 pub(super) fn l2_send_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
     req: EncryptedL3CommandPacket<'_>,
+    config: Config,
+    stats: &mut TransportStats,
     l2_buf: &'a mut [u8],
     spi: &'a mut SPI,
     cs: &'a mut Option<CS>,
 ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+    if config.command_delay_ns > 0 {
+        l1_delay_ns(spi, cs, config.command_delay_ns)?;
+    }
     let cmd_size = usize::from(req.cmd_size());
     // Number of chunks to be send
-    let chunk_num = (L3_CMD_SIZE_SIZE + cmd_size + L3_TAG_SIZE)
-        .checked_div(L2_CHUNK_MAX_DATA_SIZE)
-        // Safety: Expect is safe here since L2_CHUNK_MAX_DATA_SIZE > 0
-        .expect("L2_CHUNK_MAX_DATA_SIZE not to equal 0")
-        + 1;
-    let chunk_last_len = (L3_RES_SIZE_SIZE + cmd_size + L3_TAG_SIZE)
-        .checked_rem(L2_CHUNK_MAX_DATA_SIZE)
-        // Safety: Expect is safe here since L2_CHUNK_MAX_DATA_SIZE > 0
-        .expect("L2_CHUNK_MAX_DATA_SIZE not to equal 0");
+    #[expect(clippy::integer_division_remainder_used)]
+    let chunk_num = (L3_CMD_SIZE_SIZE + cmd_size + L3_TAG_SIZE) / L2_CHUNK_MAX_DATA_SIZE + 1;
+    record!("chunks", chunk_num);
+    #[expect(clippy::integer_division_remainder_used)]
+    let chunk_last_len = (L3_RES_SIZE_SIZE + cmd_size + L3_TAG_SIZE) % L2_CHUNK_MAX_DATA_SIZE;
 
     let cmd_size = req.cmd_size();
     let cmd_size = cmd_size.as_bytes();
@@ -451,29 +1297,35 @@ pub(super) fn l2_send_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
             L2_CHUNK_MAX_DATA_SIZE
         };
         l2_buf.fill(0);
-        l2_buf[0] = L2RequestId::EncryptedCmdReq as u8;
-        l2_buf[1] = n_in_chunk as u8;
-        for n in 0..n_in_chunk {
-            l2_buf[n + 2] = iter.next()
-            // Safety: Expect is safe here since the for-loops will not draw more items than are in `iter`.
-            .expect("item to be present");
-        }
-
         // Since L2RequestFrame is not used here, CRC needs to be calculated manually
         // and written after the remaining data in l2_buf.
-        let mut crc = Crc16::new();
         let eod = 2 + n_in_chunk;
-        crc.update(&l2_buf[..eod]);
-        let crc = crc.get().to_be_bytes();
-        l2_buf[eod..eod + 2].copy_from_slice(&crc[..]);
+        let (frame, rest) = l2_buf
+            .split_at_mut_checked(eod)
+            .ok_or(Error::RequestExceedsSize)?;
+        let (header, data) = frame.split_at_mut(2);
+        header.copy_from_slice(&[L2RequestId::EncryptedCmdReq as u8, n_in_chunk as u8]);
+        for (byte, item) in data.iter_mut().zip(&mut iter) {
+            *byte = item;
+        }
+        let mut crc = Crc16::new();
+        crc.update(frame);
+        rest.get_mut(..2)
+            .ok_or(Error::RequestExceedsSize)?
+            .copy_from_slice(&crc.get().to_be_bytes());
 
         // TODO original driver uses l1_write and l1_read here without retries.
-        let _ = l2_transfer_helper(None, l2_buf, spi, cs)?;
+        let _ = l2_transfer_helper(None, config, stats, l2_buf, spi, cs)?;
     }
     Ok(())
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(chunks = tracing::field::Empty))
+)]
 pub(super) fn l2_receive_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
+    config: Config,
     l2_buf: &'a mut [u8],
     l3_buf: &'a mut ArrayVec<u8, { L3_FRAME_MAX_SIZE }>,
     spi: &'a mut SPI,
@@ -482,7 +1334,7 @@ pub(super) fn l2_receive_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
     l3_buf.clear();
     let mut i = 0;
     while i <= L3_CMD_DATA_SIZE_MAX.saturating_div(L2_CMD_REQ_LEN) {
-        l1_read(l2_buf, spi, cs)?;
+        l1_read(l2_buf, config.busy_poll, spi, cs)?;
         let res = L2ResponseFrame::from_bytes(l2_buf)?;
         if !res.check_frame() {
             return Err(Error::InvalidL2Response);
@@ -495,9 +1347,14 @@ pub(super) fn l2_receive_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
                 i += 1;
             },
             ResponseStatus::ResOk => {
+                record!("chunks", i + 1);
                 return Ok(L3ResultPacket::from_bytes(l3_buf)?);
             },
-            _ => return Err(Error::L3CmdFailed),
+            _ => {
+                return Err(Error::L2ResponseError(
+                    res.context(L2RequestId::EncryptedCmdReq as u8),
+                ));
+            },
         }
     }
     Err(Error::L3CmdFailed)
@@ -506,15 +1363,17 @@ pub(super) fn l2_receive_encrypted_cmd<'a, SPI: SpiDevice, CS: OutputPin>(
 fn get_info_req<'a, SPI: SpiDevice, CS: OutputPin>(
     req: InfoReq,
     block: u8,
+    config: Config,
+    stats: &mut TransportStats,
     l2_buf: &'a mut [u8],
     spi: &'a mut SPI,
     cs: &'a mut Option<CS>,
 ) -> Result<L2ResponseFrame<'a>, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
 {
     let data = [&[req as u8][..], &[block][..]];
-    let frame = L2RequestFrame::new(L2RequestId::GetInfo as u8, &data[..]);
+    let frame = L2RequestFrame::new(L2RequestId::GetInfo as u8, &data[..])?;
 
-    l2_transfer(frame, l2_buf, spi, cs)
+    l2_transfer(frame, config, stats, l2_buf, spi, cs)
 }
 
 #[expect(clippy::too_many_arguments)]
@@ -572,34 +1431,106 @@ fn process_handshake<X: X25519>(
 
 #[cfg(test)]
 mod test {
+    use core::convert::Infallible;
+
+    use aes_gcm::aead::arrayvec::ArrayVec;
+    use dummy_pin::DummyPin;
+    use nom::error::ErrorKind;
+    use proptest::collection::vec;
+    use proptest::prelude::any;
+    use proptest::prop_assert;
+    use proptest::prop_assert_eq;
+    use proptest::proptest;
+    use proptest::sample::Index;
+    use proptest::sample::select;
     use x25519_dalek::PublicKey;
     use x25519_dalek::StaticSecret;
     use zerocopy::big_endian::U16;
 
     use crate::Aes256GcmKey;
+    use crate::Error;
     use crate::FromBytes;
+    use crate::L2_CHUNK_MAX_DATA_SIZE;
+    use crate::L2_MAX_FRAME_SIZE;
     use crate::Nonce;
+    use crate::ParsingError;
+    use crate::Tropic01;
+    use crate::crc16::Crc16;
     use crate::crypto::X25519Dalek;
     use crate::crypto::aesgcm_decrypt;
     use crate::crypto::hkdf;
     use crate::crypto::sha256_sequence;
     use crate::keys::SH0PRIV;
     use crate::keys::SH0PUB;
+    use crate::lt_2::ArrayString;
+    use crate::lt_2::FirmwareVersion;
+    use crate::lt_2::L2_GET_INFO_REQ_CERT_SIZE;
     use crate::lt_2::L2RequestFrame;
+    use crate::lt_2::L2RequestId;
     use crate::lt_2::L2ResponseFrame;
     use crate::lt_2::PROTOCOL_NAME;
+    use crate::lt_2::PublicKeyError;
+    use crate::lt_2::ResponseStatus;
+    use crate::lt_2::X509Certificate;
+    use crate::lt_2::base64_chunk;
+    use crate::lt_2::cert_read_len;
     use crate::lt_2::process_handshake;
+    use crate::mock::Handler;
+    use crate::mock::MockChip;
+    use crate::pinning::PinSet;
+
+    /// Minimal certificate with an X25519 public key of `0x5a` bytes.
+    fn test_certificate() -> [u8; L2_GET_INFO_REQ_CERT_SIZE] {
+        let header = [
+            0x30, 0x4f, // Certificate
+            0x30, 0x41, // tbsCertificate
+            0xa0, 0x03, 0x02, 0x01, 0x02, // version
+            0x02, 0x01, 0x01, // serialNumber
+            0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, // signature
+            0x30, 0x00, // issuer
+            0x30, 0x00, // validity
+            0x30, 0x00, // subject
+            0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x6e, // subjectPublicKeyInfo
+            0x03, 0x21, 0x00,
+        ];
+        let trailer = [
+            0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, // signatureAlgorithm
+            0x03, 0x03, 0x00, 0xaa, 0xbb, // signatureValue
+        ];
+        let mut cert = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        cert[..header.len()].copy_from_slice(&header);
+        cert[header.len()..header.len() + 32].fill(0x5a);
+        cert[header.len() + 32..header.len() + 32 + trailer.len()].copy_from_slice(&trailer);
+        cert
+    }
 
     #[test]
     fn test_l2_req_frame_correct() {
         let data = [&[0x01u8, 0x01u8][..]];
-        let req = L2RequestFrame::new(0x01, &data[..]);
+        let req = L2RequestFrame::new::<(), ()>(0x01, &data[..]).unwrap();
 
         assert_eq!(0x01, req.id);
         assert_eq!(0x02, req.len);
         assert_eq!(&data, req.data);
 
         assert_eq!(U16::from_bytes([0x2e, 0x12]), req.crc);
+
+        let mut buf = [0; 6];
+        assert_eq!(req.write_to::<(), ()>(&mut buf).unwrap(), 6);
+        assert!(matches!(
+            req.write_to::<(), ()>(&mut buf[..5]),
+            Err(Error::RequestExceedsSize)
+        ));
+    }
+    #[test]
+    fn test_l2_req_frame_too_long() {
+        let head = [0; L2_CHUNK_MAX_DATA_SIZE];
+        let data = [&head[..], &[0x01][..]];
+        assert!(matches!(
+            L2RequestFrame::new::<(), ()>(0x01, &data[..]),
+            Err(Error::RequestExceedsSize)
+        ));
+        assert!(L2RequestFrame::new::<(), ()>(0x01, &data[..1]).is_ok());
     }
     #[test]
     fn test_l2_res_frame_correct() {
@@ -608,6 +1539,519 @@ mod test {
         assert_eq!(frame.crc, 0x2e12);
     }
 
+    #[test]
+    fn firmware_version_works() {
+        let version = FirmwareVersion::new([0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(version.major(), 1);
+        assert_eq!(version.minor(), 2);
+        assert_eq!(version.patch(), 3);
+        assert_eq!(version.build(), 4);
+        assert!(!version.is_bootloader());
+
+        let version = FirmwareVersion::new([0x00, 0x01, 0x00, 0x82]);
+        assert_eq!(version.major(), 2);
+        assert!(version.is_bootloader());
+        let mut s = ArrayString::<16>::new();
+        core::fmt::write(&mut s, format_args!("{version}")).unwrap();
+        assert_eq!(s.as_str(), "2.0.1+0");
+    }
+
+    #[test]
+    fn l2_response_context_works() {
+        let data = [0x01, 0x7f, 0x01, 0x01, 0x00, 0x00];
+        let frame = L2ResponseFrame::from_bytes(&data).unwrap();
+        let context = frame.context(L2RequestId::GetInfo as u8);
+        assert_eq!(context.req_id(), 0x01);
+        assert_eq!(context.status(), ResponseStatus::GenErr);
+        assert_eq!(context.data_len(), 1);
+    }
+
+    /// Encode an L2 response frame the way the chip sends it, returning its
+    /// length.
+    fn encode_l2_response(status: ResponseStatus, data: &[u8], buf: &mut [u8]) -> usize {
+        let mut crc = Crc16::new();
+        crc.update(&[status as u8]);
+        crc.update(&[data.len() as u8]);
+        crc.update(data);
+        buf[0] = 0x01;
+        buf[1] = status as u8;
+        buf[2] = data.len() as u8;
+        buf[3..3 + data.len()].copy_from_slice(data);
+        buf[3 + data.len()..5 + data.len()].copy_from_slice(&crc.get().to_be_bytes());
+        5 + data.len()
+    }
+
+    proptest! {
+        #[test]
+        fn l2_req_frame_round_trip_works(
+            id: u8,
+            data in vec(any::<u8>(), 0..=L2_CHUNK_MAX_DATA_SIZE),
+            split: Index,
+        ) {
+            let (head, tail) = data.split_at(split.index(data.len() + 1));
+            let chunks = [head, tail];
+            let req = L2RequestFrame::new::<(), ()>(id, &chunks).unwrap();
+            let mut buf = [0; L2_MAX_FRAME_SIZE];
+            let n = req.write_to::<(), ()>(&mut buf).unwrap();
+
+            prop_assert_eq!(n, data.len() + 4);
+            prop_assert_eq!(buf[0], id);
+            prop_assert_eq!(usize::from(buf[1]), data.len());
+            prop_assert_eq!(&buf[2..n - 2], &data[..]);
+            let mut crc = Crc16::new();
+            crc.update(&buf[..n - 2]);
+            prop_assert_eq!(crc.get().to_be_bytes(), [buf[n - 2], buf[n - 1]]);
+        }
+
+        #[test]
+        fn l2_res_frame_round_trip_works(
+            status in select(&[
+                ResponseStatus::ReqOk,
+                ResponseStatus::ResOk,
+                ResponseStatus::ReqCont,
+                ResponseStatus::ResCont,
+                ResponseStatus::GenErr,
+            ][..]),
+            data in vec(any::<u8>(), 0..=u8::MAX as usize),
+        ) {
+            let mut buf = [0; 3 + u8::MAX as usize + 2];
+            let n = encode_l2_response(status, &data, &mut buf);
+            let frame = L2ResponseFrame::from_bytes(&buf[..n]).unwrap();
+
+            prop_assert_eq!(frame.resp_status, status);
+            prop_assert_eq!(frame.resp_data(), &data[..]);
+            prop_assert!(frame.check_frame());
+        }
+
+        #[test]
+        fn l2_res_frame_truncation_works(
+            data in vec(any::<u8>(), 0..=u8::MAX as usize),
+            cut: Index,
+        ) {
+            let mut buf = [0; 3 + u8::MAX as usize + 2];
+            let n = encode_l2_response(ResponseStatus::ResOk, &data, &mut buf);
+            let result = L2ResponseFrame::from_bytes(&buf[..cut.index(n)]);
+
+            prop_assert!(matches!(result, Err(ParsingError::Error(ErrorKind::Eof))));
+        }
+
+        #[test]
+        fn l2_res_frame_arbitrary_bytes_works(data in vec(any::<u8>(), 0..=L2_MAX_FRAME_SIZE + 1)) {
+            if let Ok(frame) = L2ResponseFrame::from_bytes(&data) {
+                prop_assert_eq!(frame.resp_data().len(), usize::from(frame.len));
+                let _ = frame.check_frame();
+            }
+        }
+    }
+
+    #[test]
+    fn cert_public_key_works() {
+        let data = test_certificate();
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.public_key().unwrap(), &[0x5a; 32]);
+        let stpub: PublicKey = cert.stpub().unwrap();
+        assert_eq!(stpub.as_bytes(), &[0x5a; 32]);
+    }
+
+    #[test]
+    fn cert_extensions_works() {
+        let base = test_certificate();
+        let extensions = [
+            0xa3, 0x1e, 0x30, 0x1c, // extensions
+            0x30, 0x0c, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x02, 0x30, 0x00,
+            0x30, 0x0c, 0x06, 0x05, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x04, 0x03, 0x02, 0x01, 0x07,
+        ];
+        let tbs_end = 4 + 0x41;
+        let mut data = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        data[..tbs_end].copy_from_slice(&base[..tbs_end]);
+        data[1] += 0x20;
+        data[3] += 0x20;
+        data[tbs_end..tbs_end + 0x20].copy_from_slice(&extensions);
+        data[tbs_end + 0x20..0x71].copy_from_slice(&base[tbs_end..0x51]);
+
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.public_key().unwrap(), &[0x5a; 32]);
+        let mut iter = cert.extensions().unwrap();
+        let basic_constraints = iter.next().unwrap().unwrap();
+        assert_eq!(basic_constraints.oid(), &[0x55, 0x1d, 0x13]);
+        assert!(basic_constraints.is_critical());
+        assert_eq!(basic_constraints.value(), &[0x30, 0x00]);
+        let vendor = iter.next().unwrap().unwrap();
+        assert!(!vendor.is_critical());
+        assert_eq!(vendor.value(), &[0x02, 0x01, 0x07]);
+        assert!(iter.next().is_none());
+
+        let vendor_oid = [0x2b, 0x06, 0x01, 0x04, 0x01];
+        assert_eq!(cert.extension(&vendor_oid).unwrap(), Some(vendor));
+        assert_eq!(cert.extension(&[0x55, 0x1d, 0x0e]).unwrap(), None);
+
+        let cert = X509Certificate::new(&base);
+        assert!(cert.extensions().unwrap().next().is_none());
+    }
+
+    #[test]
+    fn base64_chunk_works() {
+        assert_eq!(base64_chunk(b"Man").as_str(), "TWFu");
+        assert_eq!(base64_chunk(b"Ma").as_str(), "TWE=");
+        assert_eq!(base64_chunk(b"M").as_str(), "TQ==");
+    }
+
+    /// Certificate like [test_certificate] with the given content of the
+    /// `serialNumber` INTEGER and of the `subject` SEQUENCE.
+    fn certificate_with(serial_number: &[u8], subject: &[u8]) -> [u8; L2_GET_INFO_REQ_CERT_SIZE] {
+        fn push_tlv(buf: &mut ArrayVec<u8, 256>, tag: u8, value: &[u8]) {
+            // Only short form lengths
+            assert!(value.len() < 0x80);
+            buf.push(tag);
+            buf.push(value.len() as u8);
+            buf.try_extend_from_slice(value).unwrap();
+        }
+        let base = test_certificate();
+        let mut tbs = ArrayVec::<u8, 256>::new();
+        // version
+        tbs.try_extend_from_slice(&base[4..9]).unwrap();
+        push_tlv(&mut tbs, 0x02, serial_number);
+        // signature, issuer, validity
+        tbs.try_extend_from_slice(&base[12..23]).unwrap();
+        push_tlv(&mut tbs, 0x30, subject);
+        // subjectPublicKeyInfo
+        tbs.try_extend_from_slice(&base[25..69]).unwrap();
+        let mut cert = ArrayVec::<u8, 256>::new();
+        push_tlv(&mut cert, 0x30, &tbs);
+        // signatureAlgorithm, signatureValue
+        cert.try_extend_from_slice(&base[69..0x51]).unwrap();
+        let mut data = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        data[..2].copy_from_slice(&[0x30, cert.len() as u8]);
+        data[2..2 + cert.len()].copy_from_slice(&cert);
+        data
+    }
+
+    /// Subject with a single attribute `oid` holding a PrintableString
+    fn subject_with(oid: &[u8], value: &[u8]) -> ArrayVec<u8, 128> {
+        let mut subject = ArrayVec::new();
+        subject
+            .try_extend_from_slice(&[0x31, (value.len() + 9) as u8])
+            .unwrap();
+        subject
+            .try_extend_from_slice(&[0x30, (value.len() + 7) as u8, 0x06, 0x03])
+            .unwrap();
+        subject.try_extend_from_slice(oid).unwrap();
+        subject
+            .try_extend_from_slice(&[0x13, value.len() as u8])
+            .unwrap();
+        subject.try_extend_from_slice(value).unwrap();
+        subject
+    }
+
+    #[test]
+    fn certificate_with_works() {
+        let data = certificate_with(&[0x01], &[]);
+        assert_eq!(data, test_certificate());
+    }
+
+    #[test]
+    fn cert_subject_attribute_works() {
+        let subject = subject_with(&[0x55, 0x04, 0x03], b"TROPIC01");
+        let data = certificate_with(&[0x01], &subject);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(
+            cert.subject_attribute(&[0x55, 0x04, 0x03]),
+            Ok(Some(&b"TROPIC01"[..]))
+        );
+        assert_eq!(cert.subject_attribute(&[0x55, 0x04, 0x05]), Ok(None));
+        assert_eq!(cert.public_key().unwrap(), &[0x5a; 32]);
+    }
+
+    #[test]
+    fn cert_chip_serial_number_works() {
+        let ser_num = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+            0x0f, 0xab,
+        ];
+        let subject = subject_with(&[0x55, 0x04, 0x05], b"0102030405060708090A0B0C0D0E0FAB");
+        let data = certificate_with(&[0x01], &subject);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.chip_serial_number(), Ok(Some(ser_num)));
+
+        // The attribute takes precedence over the certificate's serial number
+        let subject = subject_with(&[0x55, 0x04, 0x05], b"none");
+        let data = certificate_with(&ser_num, &subject);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.chip_serial_number(), Ok(None));
+
+        // A SER_NUM merely contained in the subject is not accepted
+        let subject = subject_with(&[0x55, 0x04, 0x03], &ser_num);
+        let data = certificate_with(&[0x01], &subject);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.chip_serial_number(), Ok(None));
+
+        let data = certificate_with(&ser_num, &[]);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.chip_serial_number(), Ok(Some(ser_num)));
+        let mut serial_number = [0xff; 17];
+        serial_number[0] = 0x00;
+        let data = certificate_with(&serial_number, &[]);
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.chip_serial_number(), Ok(Some([0xff; 16])));
+
+        let data = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        let cert = X509Certificate::new(&data);
+        assert!(cert.chip_serial_number().is_err());
+    }
+
+    /// [test_certificate] behind the header of a certificate store as sent
+    /// by newer firmware: version, number of certificates and their lengths.
+    ///
+    /// Constructed by hand, the lengths of the other certificates are zero.
+    fn test_certificate_store() -> [u8; L2_GET_INFO_REQ_CERT_SIZE] {
+        let header = [0x01, 0x04, 0x00, 0x51, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let mut store = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        store[..header.len()].copy_from_slice(&header);
+        store[header.len()..]
+            .copy_from_slice(&test_certificate()[..L2_GET_INFO_REQ_CERT_SIZE - header.len()]);
+        store
+    }
+
+    #[test]
+    fn cert_read_len_works() {
+        assert_eq!(cert_read_len(&test_certificate()[..128]), 0x51);
+        assert_eq!(
+            cert_read_len(&test_certificate_store()[..128]),
+            L2_GET_INFO_REQ_CERT_SIZE
+        );
+        assert_eq!(cert_read_len(&[0; 128]), L2_GET_INFO_REQ_CERT_SIZE);
+        assert_eq!(
+            cert_read_len(&[0x30, 0x82, 0xff, 0xff]),
+            L2_GET_INFO_REQ_CERT_SIZE
+        );
+    }
+
+    #[test]
+    fn cert_as_der_strips_padding() {
+        let data = test_certificate();
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.as_der().unwrap(), &data[..0x51]);
+    }
+
+    #[test]
+    fn cert_write_pem_works() {
+        let data = test_certificate();
+        let cert = X509Certificate::new(&data);
+        let mut pem = ArrayString::<256>::new();
+        cert.write_pem(&mut pem).unwrap();
+        let expected = "-----BEGIN CERTIFICATE-----\n\
+                        ME8wQaADAgECAgEBMAUGAytlcDAAMAAwADAqMAUGAytlbgMhAFpaWlpaWlpaWlpa\n\
+                        WlpaWlpaWlpaWlpaWlpaWlpaWlpaMAUGAytlcAMDAKq7\n\
+                        -----END CERTIFICATE-----\n";
+        assert_eq!(pem.as_str(), expected);
+    }
+
+    #[test]
+    fn cert_fingerprint_works() {
+        let data = test_certificate();
+        let cert = X509Certificate::new(&data);
+        let expected = [
+            0x2c, 0x6d, 0xc3, 0xf4, 0xfd, 0xd0, 0x01, 0xa6, 0x3d, 0xf6, 0x63, 0x46, 0x5f, 0xac,
+            0x9e, 0x90, 0xde, 0x36, 0x1c, 0x80, 0x78, 0x52, 0x82, 0x61, 0x45, 0x04, 0xf4, 0x1d,
+            0x38, 0x1d, 0xa6, 0x9e,
+        ];
+        assert_eq!(cert.fingerprint().unwrap(), expected);
+    }
+
+    #[test]
+    fn cert_public_key_rejects_other_algorithms() {
+        let mut data = test_certificate();
+        // Replace X25519 with the Ed25519 OID in the subjectPublicKeyInfo
+        data[33] = 0x70;
+        // The key is not searched for
+        data[37..42].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        let cert = X509Certificate::new(&data);
+        assert!(matches!(
+            cert.public_key(),
+            Err(PublicKeyError::UnsupportedAlgorithm)
+        ));
+    }
+
+    #[test]
+    fn cert_public_key_rejects_empty_certificate() {
+        let data = [0; L2_GET_INFO_REQ_CERT_SIZE];
+        let cert = X509Certificate::new(&data);
+        assert!(matches!(
+            cert.public_key(),
+            Err(PublicKeyError::MalformedCertificate(_))
+        ));
+    }
+
+    #[test]
+    fn cert_store_works() {
+        let data = test_certificate_store();
+        let cert = X509Certificate::new(&data);
+        assert_eq!(cert.as_der().unwrap(), &data[10..10 + 0x51]);
+        assert_eq!(cert.public_key().unwrap(), &[0x5a; 32]);
+        assert_eq!(cert.serial_number().unwrap(), &[0x01]);
+
+        let mut data = test_certificate_store();
+        // A store without certificates
+        data[1] = 0;
+        assert!(X509Certificate::new(&data).as_der().is_err());
+    }
+
+    #[test]
+    fn cert_public_key_search_works() {
+        // A certificate running past the bytes read from the chip
+        let mut data = [0xff; L2_GET_INFO_REQ_CERT_SIZE];
+        data[..4].copy_from_slice(&[0x30, 0x82, 0x02, 0x10]);
+        data[100..105].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        data[105..137].fill(0x77);
+        let cert = X509Certificate::new(&data);
+        assert!(cert.as_der().is_err());
+        assert_eq!(cert.public_key().unwrap(), &[0x77; 32]);
+
+        // The key must not run past the end of the data.
+        data[100..105].fill(0xff);
+        data[500..505].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        assert!(X509Certificate::new(&data).public_key().is_err());
+
+        // Data which is not a certificate is not searched.
+        let mut data = [0xff; L2_GET_INFO_REQ_CERT_SIZE];
+        data[100..105].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        assert!(X509Certificate::new(&data).public_key().is_err());
+
+        // Unparsable certificates are searched up to their end only.
+        let mut data = test_certificate();
+        // tbsCertificate with a SET tag
+        data[2] = 0x31;
+        let cert = X509Certificate::new(&data);
+        assert!(cert.as_der().is_ok());
+        assert_eq!(cert.public_key().unwrap(), &[0x5a; 32]);
+        data[35] = 0;
+        data[0x51..0x56].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        data[0x56..0x76].fill(0x77);
+        assert!(matches!(
+            X509Certificate::new(&data).public_key(),
+            Err(PublicKeyError::MalformedCertificate(_))
+        ));
+    }
+
+    #[test]
+    fn cert_identity_works() {
+        let data = test_certificate();
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data);
+        assert_eq!(tropic01.stpub().unwrap(), [0x5a; 32]);
+        assert_eq!(
+            tropic01.cert_fingerprint().unwrap(),
+            X509Certificate::new(&data).fingerprint().unwrap()
+        );
+
+        // The key is found by searching, but the certificate runs past the bytes read
+        // and can not be fingerprinted.
+        let mut data = [0xff; L2_GET_INFO_REQ_CERT_SIZE];
+        data[..4].copy_from_slice(&[0x30, 0x82, 0x02, 0x10]);
+        data[100..105].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data);
+        assert!(matches!(
+            tropic01.cert_fingerprint(),
+            Err(Error::MalformedCertificate(_))
+        ));
+    }
+
+    // Handshake of a chip with the static key HANDSHAKE_STPUB with a host using
+    // SH0PUB in pairing key slot 0 and the ephemeral key HANDSHAKE_EHPUB
+    const HANDSHAKE_EHPUB: [u8; 32] = [
+        0x42, 0xd2, 0x27, 0x0, 0x0, 0xb9, 0xea, 0x70, 0xb6, 0xb8, 0x7c, 0xf9, 0x61, 0x6, 0xca,
+        0x3f, 0x3a, 0xd7, 0xe1, 0x2, 0xcc, 0xc9, 0x41, 0xdb, 0xb9, 0x91, 0x72, 0x8c, 0xa0, 0x89,
+        0xcd, 0x56,
+    ];
+    const HANDSHAKE_EHPRIV: [u8; 32] = [
+        0x18, 0x70, 0x0, 0x0, 0xb3, 0x8, 0x0, 0x0, 0xc9, 0xad, 0x0, 0x0, 0x29, 0xb9, 0x0, 0x0,
+        0x14, 0x6e, 0x0, 0x0, 0x2c, 0xde, 0x0, 0x0, 0xbd, 0x45, 0x0, 0x0, 0x1f, 0x56, 0x0, 0x0,
+    ];
+    const HANDSHAKE_ETPUB: [u8; 32] = [
+        0x16, 0xf6, 0xa5, 0xf9, 0x76, 0x11, 0x2b, 0xe5, 0xfe, 0x7b, 0x2c, 0x7, 0xfc, 0xa8, 0x6c,
+        0x43, 0xb1, 0xc9, 0x31, 0x51, 0xde, 0xce, 0x75, 0x5b, 0x79, 0x38, 0xe8, 0xde, 0x17, 0x7b,
+        0x61, 0x3c,
+    ];
+    const HANDSHAKE_STPUB: [u8; 32] = [
+        0x7c, 0xcc, 0x66, 0x64, 0x90, 0x36, 0xcd, 0x66, 0xa5, 0x52, 0xef, 0x2d, 0x19, 0x7a, 0xae,
+        0xf5, 0xc7, 0x4e, 0x70, 0x4f, 0xf7, 0x1b, 0x8d, 0xea, 0x70, 0xb, 0xec, 0x65, 0xca, 0xf9,
+        0xdf, 0x1f,
+    ];
+    const HANDSHAKE_TTAUTH: [u8; 16] = [
+        0xe4, 0x1d, 0xaa, 0x79, 0x39, 0xde, 0x59, 0xe3, 0x77, 0x4c, 0x29, 0x3d, 0x1c, 0x86, 0xa3,
+        0x91,
+    ];
+
+    /// Start a session with a mock chip answering the handshake of the
+    /// `HANDSHAKE_*` test vectors.
+    fn start_test_session(
+        tropic01: &mut Tropic01<MockChip<impl Handler>, DummyPin>,
+    ) -> Result<(), Error<Infallible, Infallible>> {
+        tropic01.session = None;
+        tropic01
+            .spi
+            .set_handshake_response(HANDSHAKE_ETPUB, HANDSHAKE_TTAUTH);
+        tropic01.session_start(
+            &X25519Dalek,
+            PublicKey::from(SH0PUB),
+            StaticSecret::from(SH0PRIV),
+            PublicKey::from(HANDSHAKE_EHPUB),
+            StaticSecret::from(HANDSHAKE_EHPRIV),
+            0,
+        )
+    }
+
+    #[test]
+    fn session_start_unfingerprintable_certificate_works() {
+        // The key is only found by searching the certificate, which runs past the
+        // bytes read.
+        let mut data = [0xff; L2_GET_INFO_REQ_CERT_SIZE];
+        data[..4].copy_from_slice(&[0x30, 0x82, 0x02, 0x10]);
+        data[100..105].copy_from_slice(&[0x65, 0x6e, 0x03, 0x21, 0x00]);
+        data[105..137].copy_from_slice(&HANDSHAKE_STPUB);
+
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data);
+        start_test_session(&mut tropic01).unwrap();
+        assert!(tropic01.session.is_some());
+
+        // Pinning needs the fingerprint.
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data).with_pin_set(PinSet::new());
+        assert!(matches!(
+            start_test_session(&mut tropic01),
+            Err(Error::MalformedCertificate(_))
+        ));
+        assert!(tropic01.session.is_none());
+    }
+
+    #[test]
+    fn session_start_authenticated_works() {
+        let mut data = test_certificate();
+        data[37..69].copy_from_slice(&HANDSHAKE_STPUB);
+        let fingerprint = X509Certificate::new(&data).fingerprint().unwrap();
+
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data).with_authenticated_sessions();
+        assert!(matches!(
+            start_test_session(&mut tropic01),
+            Err(Error::UnauthenticatedSession)
+        ));
+        assert!(tropic01.session.is_none());
+
+        let mut pins = PinSet::new();
+        pins.push(fingerprint).unwrap();
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data)
+            .with_authenticated_sessions()
+            .with_pin_set(pins);
+        start_test_session(&mut tropic01).unwrap();
+        assert!(tropic01.session.is_some());
+
+        let mut tropic01 = MockChip::with_cert(|_, _| (), &data)
+            .with_authenticated_sessions()
+            .with_pin_set(PinSet::new());
+        assert!(matches!(
+            start_test_session(&mut tropic01),
+            Err(Error::CertificatePinMismatch)
+        ));
+    }
+
     #[test]
     fn session_start_works() {
         let pkey_index = 0;
@@ -616,32 +2060,14 @@ mod test {
             0x5e, 0x29, 0x30, 0xad, 0x4f, 0x68, 0x48, 0x1e, 0x8c, 0xa6, 0x90, 0x34, 0x7e, 0xab,
             0xbe, 0xec, 0xfd, 0xc8,
         ];
-        let ehpub: [u8; 32] = [
-            0x42, 0xd2, 0x27, 0x0, 0x0, 0xb9, 0xea, 0x70, 0xb6, 0xb8, 0x7c, 0xf9, 0x61, 0x6, 0xca,
-            0x3f, 0x3a, 0xd7, 0xe1, 0x2, 0xcc, 0xc9, 0x41, 0xdb, 0xb9, 0x91, 0x72, 0x8c, 0xa0,
-            0x89, 0xcd, 0x56,
-        ];
-        let ehpriv: [u8; 32] = [
-            0x18, 0x70, 0x0, 0x0, 0xb3, 0x8, 0x0, 0x0, 0xc9, 0xad, 0x0, 0x0, 0x29, 0xb9, 0x0, 0x0,
-            0x14, 0x6e, 0x0, 0x0, 0x2c, 0xde, 0x0, 0x0, 0xbd, 0x45, 0x0, 0x0, 0x1f, 0x56, 0x0, 0x0,
-        ];
-        let etpub: [u8; 32] = [
-            0x16, 0xf6, 0xa5, 0xf9, 0x76, 0x11, 0x2b, 0xe5, 0xfe, 0x7b, 0x2c, 0x7, 0xfc, 0xa8,
-            0x6c, 0x43, 0xb1, 0xc9, 0x31, 0x51, 0xde, 0xce, 0x75, 0x5b, 0x79, 0x38, 0xe8, 0xde,
-            0x17, 0x7b, 0x61, 0x3c,
-        ];
+        let ehpub = HANDSHAKE_EHPUB;
+        let ehpriv = HANDSHAKE_EHPRIV;
+        let etpub = HANDSHAKE_ETPUB;
         let shipriv = StaticSecret::from(SH0PRIV);
         let shipub = PublicKey::from(SH0PUB);
-        let stpub: [u8; 32] = [
-            0x7c, 0xcc, 0x66, 0x64, 0x90, 0x36, 0xcd, 0x66, 0xa5, 0x52, 0xef, 0x2d, 0x19, 0x7a,
-            0xae, 0xf5, 0xc7, 0x4e, 0x70, 0x4f, 0xf7, 0x1b, 0x8d, 0xea, 0x70, 0xb, 0xec, 0x65,
-            0xca, 0xf9, 0xdf, 0x1f,
-        ];
+        let stpub = HANDSHAKE_STPUB;
 
-        let ttauth: [u8; 16] = [
-            0xe4, 0x1d, 0xaa, 0x79, 0x39, 0xde, 0x59, 0xe3, 0x77, 0x4c, 0x29, 0x3d, 0x1c, 0x86,
-            0xa3, 0x91,
-        ];
+        let ttauth = HANDSHAKE_TTAUTH;
         let expected_output1_1: [u8; 33] = [
             0xc5, 0x18, 0xd2, 0xe6, 0xfa, 0xad, 0xf3, 0x60, 0x3f, 0x9a, 0x48, 0x50, 0x10, 0xe9,
             0x83, 0x81, 0xe7, 0xba, 0xc4, 0x9f, 0x65, 0x6e, 0xb1, 0x3c, 0xbc, 0x44, 0xd1, 0x3d,
@@ -6,11 +6,15 @@ use nom_derive::Nom;
 use zerocopy::IntoBytes;
 use zerocopy::little_endian::U16;
 
+use crate::CommandClass;
 use crate::Error;
 use crate::FromBytes;
 use crate::L3_CMD_DATA_SIZE_MAX;
 use crate::L3_RES_SIZE_SIZE;
 use crate::L3_TAG_SIZE;
+use crate::Prehash;
+use crate::PrehashedSignature;
+use crate::SerialCode;
 use crate::Tropic01;
 use crate::crypto::aesgcm_decrypt;
 use crate::crypto::aesgcm_encrypt;
@@ -19,13 +23,13 @@ use crate::lt_2::l2_send_encrypted_cmd;
 
 #[derive(Clone, Debug)]
 struct DecryptedL3CommandPacket<'a> {
-    id: u8,
+    id: L3CmdId,
     data: &'a [&'a [u8]],
 }
 
 impl<'a> DecryptedL3CommandPacket<'a> {
     #[must_use]
-    pub const fn new(id: u8, data: &'a [&'a [u8]]) -> Self {
+    pub const fn new(id: L3CmdId, data: &'a [&'a [u8]]) -> Self {
         Self { id, data }
     }
 }
@@ -54,19 +58,36 @@ impl<'a> EncryptedL3CommandPacket<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 enum L3CmdId {
     Ping = 0x01,
     RandomValueGet = 0x50,
     EccKeyGenerate = 0x60,
     EccKeyRead = 0x62,
+    EccKeyErase = 0x63,
     EcDSASign = 0x70,
     EdDSASign = 0x71,
+    SerialCodeGet = 0xa0,
+}
+
+impl L3CmdId {
+    const fn class(self) -> CommandClass {
+        match self {
+            Self::EccKeyGenerate => CommandClass::KeyGeneration,
+            Self::EcDSASign | Self::EdDSASign => CommandClass::Sign,
+            Self::Ping
+            | Self::RandomValueGet
+            | Self::EccKeyRead
+            | Self::EccKeyErase
+            | Self::SerialCodeGet => CommandClass::Fast,
+        }
+    }
 }
 
 /// Represents all kinds of curves the chip supports.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Nom)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum EccCurve {
     P256 = 0x01,
@@ -103,6 +124,7 @@ struct L3ResultData<'a> {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Nom, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 enum L3ResultStatus {
     Ok = 0xc3,
@@ -114,6 +136,7 @@ enum L3ResultStatus {
 
 /// Represents all kinds of origins the chip supports.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Nom)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum EccOrigin {
     /// Key originated from the [Tropic01::ecc_key_generate] method.
@@ -123,6 +146,7 @@ pub enum EccOrigin {
 }
 
 #[derive(Debug, Clone, Nom)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct EccKeyReadResponse<'a> {
     curve: EccCurve,
     origin: EccOrigin,
@@ -155,7 +179,24 @@ struct SignResponse<'a> {
     signature: &'a [u8],
 }
 
+#[derive(Debug, Clone, Nom)]
+struct SerialCodeResponse<'a> {
+    #[nom(SkipBefore(3), Take(32))]
+    serial_code: &'a [u8],
+}
+
 impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(
+                cmd_id = packet.id as u8,
+                len = tracing::field::Empty,
+            )
+        )
+    )]
     fn lt_l3_transfer(
         &mut self,
         packet: DecryptedL3CommandPacket<'_>,
@@ -165,20 +206,17 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         self.l3_buf.clear();
 
         self.l3_buf
-            .try_extend_from_slice(&[packet.id])
-            // Safety: Expect is safe here since it is verified before that l3_buf has enough capacity, and l3_buf was just emptied.
-            .expect("packet id to fit into buffer");
+            .try_extend_from_slice(&[packet.id as u8])
+            .map_err(|_| Error::RequestExceedsSize)?;
         for data in packet.data {
             self.l3_buf
                 .try_extend_from_slice(data)
-                // Safety: This is safe since ping and eddsa_sign methods verify that their raw data does not exceed L3_CMD_DATA_SIZE_MAX.
-                .expect("packet msg to fit into buffer");
+                .map_err(|_| Error::RequestExceedsSize)?;
         }
         let len = self.l3_buf.len();
+        record!("len", len);
 
-        let size = U16::try_from(len)
-        // Safety: Expect is safe here since l3_buf capacity (L3_FRAME_MAX_SIZE) < U16::MAX.
-        .expect("cmd len to be in u16 range");
+        let size = U16::try_from(len).map_err(|_| Error::RequestExceedsSize)?;
         let tag = aesgcm_encrypt(&session.encrypt, &session.iv, b"", &mut self.l3_buf)
             .map_err(Error::Encryption)?;
 
@@ -188,13 +226,23 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
             tag,
         };
 
-        l2_send_encrypted_cmd(cmd, &mut self.l2_buf, &mut self.spi, &mut self.cs)?;
+        l2_send_encrypted_cmd(
+            cmd,
+            self.config,
+            &mut self.stats,
+            &mut self.l2_buf,
+            &mut self.spi,
+            &mut self.cs,
+        )
+        .inspect_err(|err| self.stats.record_error(err))?;
         let _ = l2_receive_encrypted_cmd(
+            self.config.for_command(packet.id.class()),
             &mut self.l2_buf,
             &mut self.l3_buf,
             &mut self.spi,
             &mut self.cs,
-        )?;
+        )
+        .inspect_err(|err| self.stats.record_error(err))?;
 
         // Remove the tag and cmd_size from the l3_buf, leaving only the encrypted data.
         //
@@ -203,8 +251,12 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         // `L3ResultPacket``
         debug_assert!(self.l3_buf.len() > L3_RES_SIZE_SIZE + L3_TAG_SIZE);
         self.l3_buf.drain(0..L3_RES_SIZE_SIZE);
-        let l3_buf_len = self.l3_buf.len();
-        let (l3_buf, tag) = self.l3_buf.split_at_mut(l3_buf_len - L3_TAG_SIZE);
+        let (l3_buf, tag) = self
+            .l3_buf
+            .len()
+            .checked_sub(L3_TAG_SIZE)
+            .and_then(|mid| self.l3_buf.split_at_mut_checked(mid))
+            .ok_or(Error::InvalidL2Response)?;
 
         aesgcm_decrypt(&session.decrypt, &session.iv, b"", tag, l3_buf)
             .map_err(Error::Decryption)?;
@@ -213,6 +265,9 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
 
         let res = L3ResultData::from_bytes(l3_buf)?;
 
+        if res.result != L3ResultStatus::Ok {
+            debug!("L3 command {:#04x} failed: {}", packet.id as u8, res.result);
+        }
         match res.result {
             L3ResultStatus::Ok => (),
             L3ResultStatus::Fail => return Err(Error::L3CmdFailed),
@@ -226,6 +281,10 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         Ok(res)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = data.len()))
+    )]
     pub fn ping(
         &mut self,
         data: &[u8],
@@ -234,32 +293,49 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
             return Err(Error::RequestExceedsSize);
         }
         let data = [data];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::Ping as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::Ping, &data[..]);
         let res = self.lt_l3_transfer(cmd_raw)?;
         Ok(res.data)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(n))
+    )]
     pub fn get_random_value(
         &mut self,
         n: u8,
     ) -> Result<&[u8], Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         let data = [&[n][..]];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::RandomValueGet as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::RandomValueGet, &data[..]);
         let res = self.lt_l3_transfer(cmd_raw)?;
-        Ok(&res.data[3..])
+        // Skip the padding in front of the random bytes.
+        let random = res.data.get(3..).ok_or(Error::InvalidL2Response)?;
+        if random.len() != usize::from(n) {
+            return Err(Error::InvalidL2Response);
+        }
+        Ok(random)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get(), curve = ?curve))
+    )]
     pub fn ecc_key_generate(
         &mut self,
         slot: zerocopy::big_endian::U16,
         curve: EccCurve,
     ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         let data = [slot.as_bytes(), &[curve as u8]];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EccKeyGenerate as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EccKeyGenerate, &data[..]);
         self.lt_l3_transfer(cmd_raw)?;
         Ok(())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get()))
+    )]
     pub fn ecc_key_read(
         &mut self,
         slot: zerocopy::big_endian::U16,
@@ -268,11 +344,29 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
         Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>,
     > {
         let data = [slot.as_bytes()];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EccKeyRead as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EccKeyRead, &data[..]);
         let res = self.lt_l3_transfer(cmd_raw)?;
         Ok(EccKeyReadResponse::from_bytes(res.data)?)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get()))
+    )]
+    pub fn ecc_key_erase(
+        &mut self,
+        slot: zerocopy::big_endian::U16,
+    ) -> Result<(), Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        let data = [slot.as_bytes()];
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EccKeyErase, &data[..]);
+        self.lt_l3_transfer(cmd_raw)?;
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get()))
+    )]
     pub fn ecdsa_sign(
         &mut self,
         slot: zerocopy::big_endian::U16,
@@ -280,16 +374,16 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
     ) -> Result<&[u8; 64], Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
         let padding = [0; 13];
         let data = [slot.as_bytes(), &padding[..], &hash[..]];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EcDSASign as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EcDSASign, &data[..]);
         let res = self.lt_l3_transfer(cmd_raw)?;
         let signature = SignResponse::from_bytes(res.data)?.signature;
-        debug_assert!(signature.len() == 64);
-        Ok(signature
-            .try_into()
-            // Safety: Expect is safe here because SignResponse verifies the signature length.
-            .expect("signature to be 64 bytes long"))
+        signature.try_into().map_err(|_| Error::InvalidL2Response)
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get(), len = msg.len()))
+    )]
     pub fn eddsa_sign(
         &mut self,
         slot: zerocopy::big_endian::U16,
@@ -301,20 +395,140 @@ impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
 
         let padding = [0; 13];
         let data = [slot.as_bytes(), &padding[..], msg];
-        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EdDSASign as u8, &data[..]);
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::EdDSASign, &data[..]);
         let res = self.lt_l3_transfer(cmd_raw)?;
         let signature = SignResponse::from_bytes(res.data)?.signature;
-        debug_assert!(signature.len() == 64);
-        Ok(signature
+        signature.try_into().map_err(|_| Error::InvalidL2Response)
+    }
+
+    /// Sign the SHA-256 digest of a message with the Ed25519 key in `slot`.
+    ///
+    /// The chip signs the 32 byte digest as the message, this is not Ed25519ph
+    /// as specified in RFC 8032. The returned signature needs to be verified
+    /// against [PrehashedSignature::signed_message].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get()))
+    )]
+    pub fn eddsa_sign_prehashed(
+        &mut self,
+        slot: zerocopy::big_endian::U16,
+        prehash: &Prehash,
+    ) -> Result<PrehashedSignature, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        let signature = self.eddsa_sign(slot, prehash.as_bytes())?;
+        Ok(PrehashedSignature::new(*prehash, *signature))
+    }
+
+    /// Read the chip's 32 byte serial code.
+    ///
+    /// The serial code is a short identifier of the chip, unlike the full
+    /// [crate::ChipId] returned by [Self::chip_id].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn serial_code_get(
+        &mut self,
+    ) -> Result<SerialCode, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        let cmd_raw = DecryptedL3CommandPacket::new(L3CmdId::SerialCodeGet, &[]);
+        let res = self.lt_l3_transfer(cmd_raw)?;
+        let serial_code = SerialCodeResponse::from_bytes(res.data)?.serial_code;
+        serial_code
             .try_into()
-            // Safety: Expect is safe here because SignResponse verifies the signature length.
-            .expect("signature to be 64 bytes long"))
+            .map(SerialCode::new)
+            .map_err(|_| Error::InvalidL2Response)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use aes_gcm::aead::arrayvec::ArrayVec;
+    use nom::error::ErrorKind;
+    use proptest::collection::vec;
+    use proptest::prelude::any;
+    use proptest::prop_assert;
+    use proptest::prop_assert_eq;
+    use proptest::proptest;
+    use proptest::sample::Index;
+    use proptest::sample::select;
+
     use super::*;
+    use crate::Aes256GcmKey;
+    use crate::L3_FRAME_MAX_SIZE;
+    use crate::Nonce;
+    use crate::ParsingError;
+    use crate::ResponseStatus;
+    use crate::mock::MockChip;
+    use crate::mock::RESULT_FAIL;
+    use crate::mock::RESULT_OK;
+
+    /// Encrypt `result` and `data` and encode them as an L3 result packet
+    /// the way the chip sends it.
+    fn encode_l3_result(
+        key: &Aes256GcmKey,
+        nonce: &Nonce,
+        result: L3ResultStatus,
+        data: &[u8],
+    ) -> ArrayVec<u8, L3_FRAME_MAX_SIZE> {
+        let mut ciphertext = ArrayVec::new();
+        ciphertext.push(result as u8);
+        ciphertext.try_extend_from_slice(data).unwrap();
+        let tag = aesgcm_encrypt(key, nonce, b"", &mut ciphertext).unwrap();
+
+        let mut packet = ArrayVec::new();
+        packet
+            .try_extend_from_slice(&(ciphertext.len() as u16).to_le_bytes())
+            .unwrap();
+        packet.try_extend_from_slice(&ciphertext).unwrap();
+        packet.try_extend_from_slice(&tag).unwrap();
+        packet
+    }
+
+    proptest! {
+        #[test]
+        fn l3_result_packet_round_trip_works(
+            key: [u8; 32],
+            iv: u64,
+            result in select(&[
+                L3ResultStatus::Ok,
+                L3ResultStatus::Fail,
+                L3ResultStatus::Unauthorized,
+                L3ResultStatus::InvalidCmd,
+                L3ResultStatus::InvalidKey,
+            ][..]),
+            data in vec(any::<u8>(), 0..L3_CMD_DATA_SIZE_MAX),
+        ) {
+            let key = Aes256GcmKey(key);
+            let nonce = Nonce(iv.into());
+            let mut packet = encode_l3_result(&key, &nonce, result, &data);
+            prop_assert!(L3ResultPacket::from_bytes(&packet).is_ok());
+
+            let len = data.len() + 1;
+            let (ciphertext, tag) = packet[L3_RES_SIZE_SIZE..].split_at_mut(len);
+            aesgcm_decrypt(&key, &nonce, b"", tag, ciphertext).unwrap();
+            let res = L3ResultData::from_bytes(ciphertext).unwrap();
+            prop_assert_eq!(res.result, result);
+            prop_assert_eq!(res.data, &data[..]);
+        }
+
+        #[test]
+        fn l3_result_packet_truncation_works(
+            data in vec(any::<u8>(), 0..=u8::MAX as usize),
+            cut: Index,
+        ) {
+            let key = Aes256GcmKey([0; 32]);
+            let packet = encode_l3_result(&key, &Nonce(0), L3ResultStatus::Ok, &data);
+            let result = L3ResultPacket::from_bytes(&packet[..cut.index(packet.len())]);
+
+            prop_assert!(matches!(result, Err(ParsingError::Error(ErrorKind::Eof))));
+        }
+
+        #[test]
+        fn l3_result_data_arbitrary_bytes_works(data in vec(any::<u8>(), 0..=u8::MAX as usize)) {
+            let _ = L3ResultPacket::from_bytes(&data);
+            if let Ok(res) = L3ResultData::from_bytes(&data) {
+                prop_assert_eq!(res.data.len() + 1, data.len());
+            }
+        }
+    }
 
     /// Verifies that L3 command IDs match the TROPIC01 specification.
     /// Reference: libtropic C SDK `src/lt_l3_api_structs.h`
@@ -336,6 +550,11 @@ mod test {
             0x62,
             "ECC_KEY_READ command ID mismatch"
         );
+        assert_eq!(
+            L3CmdId::EccKeyErase as u8,
+            0x63,
+            "ECC_KEY_ERASE command ID mismatch"
+        );
         assert_eq!(
             L3CmdId::EcDSASign as u8,
             0x70,
@@ -346,5 +565,65 @@ mod test {
             0x71,
             "EDDSA_SIGN command ID mismatch"
         );
+        assert_eq!(
+            L3CmdId::SerialCodeGet as u8,
+            0xa0,
+            "SERIAL_CODE_GET command ID mismatch"
+        );
+    }
+
+    #[test]
+    fn serial_code_response_works() {
+        let mut data = [0; 3 + 32];
+        data[3..].fill(0x5a);
+        let res = SerialCodeResponse::from_bytes(&data).unwrap();
+        assert_eq!(res.serial_code, &[0x5a; 32]);
+
+        assert!(SerialCodeResponse::from_bytes(&data[..34]).is_err());
+    }
+
+    #[test]
+    fn serial_code_get_works() {
+        let mut tropic01 = MockChip::session(|cmd, res| {
+            assert_eq!(cmd, [0xa0]);
+            res.try_extend_from_slice(&[RESULT_OK, 0, 0, 0]).unwrap();
+            res.try_extend_from_slice(&[0x5a; 32]).unwrap();
+        });
+        let serial_code = tropic01.serial_code_get().unwrap();
+        assert_eq!(serial_code.to_bytes(), [0x5a; 32]);
+    }
+
+    #[test]
+    fn serial_code_get_short_response_works() {
+        let mut tropic01 = MockChip::session(|_, res| {
+            res.try_extend_from_slice(&[RESULT_OK, 0, 0, 0]).unwrap();
+            res.try_extend_from_slice(&[0x5a; 31]).unwrap();
+        });
+        assert!(matches!(
+            tropic01.serial_code_get(),
+            Err(Error::ParsingError(_))
+        ));
+
+        let mut tropic01 = MockChip::session(|_, res| res.push(RESULT_FAIL));
+        assert!(matches!(
+            tropic01.serial_code_get(),
+            Err(Error::L3CmdFailed)
+        ));
+    }
+
+    #[test]
+    fn l3_cmd_error_statuses_works() {
+        // The chip rejects the command on L2
+        let mut tropic01 = MockChip::session(|_, res| res.push(RESULT_OK));
+        tropic01.spi.set_result_status(ResponseStatus::GenErr);
+        assert!(matches!(
+            tropic01.ping(b""),
+            Err(Error::L2ResponseError(context))
+                if context.req_id() == 0x04 && context.status() == ResponseStatus::GenErr
+        ));
+
+        // The chip processed the command but reports a failed result
+        let mut tropic01 = MockChip::session(|_, res| res.push(RESULT_FAIL));
+        assert!(matches!(tropic01.ping(b""), Err(Error::L3CmdFailed)));
     }
 }
@@ -0,0 +1,233 @@
+//! Emulation of the chip's secure session for tests
+//!
+//! [MockChip] answers the L1/L2 traffic of encrypted L3 commands. The
+//! decrypted commands are passed to a handler which provides the decrypted
+//! result, i.e. the result status followed by the result data. Certificate
+//! and handshake requests are answered with fixed responses.
+
+use core::convert::Infallible;
+
+use aes_gcm::aead::arrayvec::ArrayVec;
+use dummy_pin::DummyPin;
+use embedded_hal::spi::ErrorType as SpiErrorType;
+use embedded_hal::spi::Operation;
+use embedded_hal::spi::SpiDevice;
+
+use crate::Aes256GcmKey;
+use crate::L2_CHUNK_MAX_DATA_SIZE;
+use crate::L2_CMD_REQ_LEN;
+use crate::L3_FRAME_MAX_SIZE;
+use crate::L3_RES_SIZE_SIZE;
+use crate::L3_TAG_SIZE;
+use crate::Nonce;
+use crate::Session;
+use crate::Tropic01;
+use crate::X509Certificate;
+use crate::crc16::Crc16;
+use crate::crypto::aesgcm_decrypt;
+use crate::crypto::aesgcm_encrypt;
+use crate::lt_2::ResponseStatus;
+
+const GET_RESPONSE: u8 = 0xaa;
+const GET_INFO_REQ: u8 = 0x01;
+const HANDSHAKE_REQ: u8 = 0x02;
+const ENCRYPTED_CMD_REQ: u8 = 0x04;
+const INFO_X509_CERTIFICATE: u8 = 0x00;
+const CERT_BLOCK_SIZE: usize = 128;
+const CHIP_READY: u8 = 0x01;
+const CMD_KEY: [u8; 32] = [0x11; 32];
+const RES_KEY: [u8; 32] = [0x22; 32];
+
+/// Result status of successful L3 commands
+pub(crate) const RESULT_OK: u8 = 0xc3;
+/// Result status of failed L3 commands
+pub(crate) const RESULT_FAIL: u8 = 0x3c;
+
+/// Handler of the decrypted commands, writing the decrypted result to its
+/// second argument.
+pub(crate) trait Handler: FnMut(&[u8], &mut ArrayVec<u8, L3_FRAME_MAX_SIZE>) {}
+
+impl<F: FnMut(&[u8], &mut ArrayVec<u8, L3_FRAME_MAX_SIZE>)> Handler for F {}
+
+/// Chip in secure channel mode, answering decrypted commands with `handler`.
+pub(crate) struct MockChip<F> {
+    handler: F,
+    iv: Nonce,
+    cmd: ArrayVec<u8, L3_FRAME_MAX_SIZE>,
+    res: ArrayVec<u8, L3_FRAME_MAX_SIZE>,
+    res_pos: usize,
+    ack: Option<ResponseStatus>,
+    ack_data: ArrayVec<u8, CERT_BLOCK_SIZE>,
+    res_status: Option<ResponseStatus>,
+    cert: [u8; X509Certificate::SIZE],
+    handshake: [u8; 48],
+}
+
+impl<F: Handler> MockChip<F> {
+    /// Return a driver with a secure session to a mock chip calling `handler`
+    /// with each decrypted command.
+    pub(crate) fn session(handler: F) -> Tropic01<Self, DummyPin> {
+        Self::with_cert(handler, &[0; X509Certificate::SIZE])
+    }
+
+    /// Like [Self::session], with the chip returning `cert` when its
+    /// certificate is read.
+    pub(crate) fn with_cert(
+        handler: F,
+        cert: &[u8; X509Certificate::SIZE],
+    ) -> Tropic01<Self, DummyPin> {
+        let mut tropic01 = Tropic01::new(Self {
+            handler,
+            iv: Nonce::default(),
+            cmd: ArrayVec::new(),
+            res: ArrayVec::new(),
+            res_pos: 0,
+            ack: None,
+            ack_data: ArrayVec::new(),
+            res_status: None,
+            cert: *cert,
+            handshake: [0; 48],
+        });
+        tropic01.session = Some(Session::new(Aes256GcmKey(CMD_KEY), Aes256GcmKey(RES_KEY)));
+        tropic01
+    }
+
+    /// Answer handshake requests with the chip's ephemeral public key `etpub`
+    /// and authentication tag `ttauth`.
+    pub(crate) fn set_handshake_response(&mut self, etpub: [u8; 32], ttauth: [u8; 16]) {
+        self.handshake[..32].copy_from_slice(&etpub);
+        self.handshake[32..].copy_from_slice(&ttauth);
+    }
+
+    /// Answer the next result read with the L2 status `status` and no data,
+    /// instead of the encrypted result.
+    pub(crate) const fn set_result_status(&mut self, status: ResponseStatus) {
+        self.res_status = Some(status);
+    }
+
+    fn write(&mut self, buf: &mut [u8]) {
+        self.ack_data.clear();
+        if buf.first() == Some(&GET_INFO_REQ) && buf[2] == INFO_X509_CERTIFICATE {
+            let start = usize::from(buf[3]) * CERT_BLOCK_SIZE;
+            self.ack_data
+                .try_extend_from_slice(&self.cert[start..start + CERT_BLOCK_SIZE])
+                .unwrap();
+            self.ack = Some(ResponseStatus::ReqOk);
+        } else if buf.first() == Some(&HANDSHAKE_REQ) {
+            self.ack_data
+                .try_extend_from_slice(&self.handshake)
+                .unwrap();
+            self.ack = Some(ResponseStatus::ReqOk);
+        } else if buf.first() == Some(&ENCRYPTED_CMD_REQ) {
+            let len = usize::from(buf[1]);
+            self.cmd.try_extend_from_slice(&buf[2..2 + len]).unwrap();
+            let size = usize::from(u16::from_le_bytes([self.cmd[0], self.cmd[1]]));
+            self.ack = if self.cmd.len() < L3_RES_SIZE_SIZE + size + L3_TAG_SIZE {
+                Some(ResponseStatus::ReqCont)
+            } else {
+                self.process(size);
+                Some(ResponseStatus::ReqOk)
+            };
+        }
+        buf[0] = CHIP_READY;
+    }
+
+    fn process(&mut self, size: usize) {
+        let (cmd, tag) = self.cmd[L3_RES_SIZE_SIZE..].split_at_mut(size);
+        aesgcm_decrypt(&Aes256GcmKey(CMD_KEY), &self.iv, b"", tag, cmd).unwrap();
+
+        let mut res = ArrayVec::new();
+        (self.handler)(cmd, &mut res);
+        let tag = aesgcm_encrypt(&Aes256GcmKey(RES_KEY), &self.iv, b"", &mut res).unwrap();
+        self.iv.wrapping_inc();
+
+        self.res.clear();
+        self.res
+            .try_extend_from_slice(&u16::try_from(res.len()).unwrap().to_le_bytes())
+            .unwrap();
+        self.res.try_extend_from_slice(&res).unwrap();
+        self.res.try_extend_from_slice(&tag).unwrap();
+        self.res_pos = 0;
+        self.cmd.clear();
+    }
+
+    fn read(&mut self, buf: &mut [u8]) {
+        buf.fill(0);
+        buf[0] = CHIP_READY;
+        if buf.len() == 1 {
+            return;
+        }
+        let (status, data) = if let Some(ack) = self.ack.take() {
+            (ack, &self.ack_data[..])
+        } else if let Some(status) = self.res_status.take() {
+            (status, &[][..])
+        } else {
+            let end = self.res.len().min(self.res_pos + L2_CMD_REQ_LEN);
+            let data = &self.res[self.res_pos..end];
+            self.res_pos = end;
+            let status = if end == self.res.len() {
+                ResponseStatus::ResOk
+            } else {
+                ResponseStatus::ResCont
+            };
+            (status, data)
+        };
+        assert!(data.len() <= L2_CHUNK_MAX_DATA_SIZE);
+        let len = data.len() as u8;
+        buf[1] = status as u8;
+        buf[2] = len;
+        buf[3..3 + data.len()].copy_from_slice(data);
+        let mut crc = Crc16::new();
+        crc.update(&[status as u8, len]);
+        crc.update(data);
+        buf[3 + data.len()..5 + data.len()].copy_from_slice(&crc.get().to_be_bytes());
+    }
+}
+
+impl<F> SpiErrorType for MockChip<F> {
+    type Error = Infallible;
+}
+
+impl<F: Handler> SpiDevice for MockChip<F> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        for operation in operations {
+            match operation {
+                Operation::TransferInPlace(buf) if buf.first() == Some(&GET_RESPONSE) => {
+                    self.read(buf);
+                },
+                Operation::TransferInPlace(buf) => self.write(buf),
+                // Delays need no emulation, other operations are not used by the driver.
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+    use crate::mock::MockChip;
+    use crate::mock::RESULT_FAIL;
+    use crate::mock::RESULT_OK;
+
+    #[test]
+    fn mock_chip_works() {
+        let mut tropic01 = MockChip::session(|cmd, res| {
+            res.push(if cmd[0] == 0x01 {
+                RESULT_OK
+            } else {
+                RESULT_FAIL
+            });
+            res.try_extend_from_slice(&cmd[1..]).unwrap();
+        });
+        // Spans several L2 frames in both directions
+        let data = [0x5a; 600];
+        assert_eq!(tropic01.ping(&data).unwrap(), &data[..]);
+        assert_eq!(tropic01.ping(b"").unwrap(), b"");
+        assert!(matches!(
+            tropic01.get_random_value(1),
+            Err(Error::L3CmdFailed)
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+//! Certificate pinning
+//!
+//! A [PinSet] holds SHA-256 fingerprints of the device certificates a host is
+//! willing to talk to. When configured via [crate::Tropic01::with_pin_set],
+//! [crate::Tropic01::session_start] refuses to start a session with chips whose
+//! certificate fingerprint is not part of the set.
+
+use aes_gcm::aead::arrayvec::ArrayVec;
+
+/// Max number of fingerprints a [PinSet] can hold
+pub const PIN_SET_MAX: usize = 8;
+
+/// SHA-256 fingerprint of a DER encoded certificate
+pub type Fingerprint = [u8; 32];
+
+/// Set of allowed certificate fingerprints.
+#[derive(Debug, Clone, Default)]
+pub struct PinSet {
+    pins: ArrayVec<Fingerprint, PIN_SET_MAX>,
+}
+
+/// Returned when adding a fingerprint to a full [PinSet].
+#[derive(Debug, derive_more::Display, derive_more::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[display("Pin set can not hold more than {PIN_SET_MAX} fingerprints")]
+pub struct PinSetFull;
+
+impl PinSet {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            pins: ArrayVec::new_const(),
+        }
+    }
+
+    /// Add an allowed fingerprint.
+    pub fn push(&mut self, fingerprint: Fingerprint) -> Result<(), PinSetFull> {
+        self.pins.try_push(fingerprint).map_err(|_| PinSetFull)
+    }
+
+    /// Check whether `fingerprint` is part of the set.
+    #[must_use]
+    pub fn contains(&self, fingerprint: &Fingerprint) -> bool {
+        self.pins.contains(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pinning::PIN_SET_MAX;
+    use crate::pinning::PinSet;
+
+    #[test]
+    fn pin_set_works() {
+        let mut pins = PinSet::new();
+        assert!(!pins.contains(&[1; 32]));
+        pins.push([1; 32]).unwrap();
+        assert!(pins.contains(&[1; 32]));
+        assert!(!pins.contains(&[2; 32]));
+    }
+
+    #[test]
+    fn pin_set_rejects_overflow() {
+        let mut pins = PinSet::new();
+        for i in 0..PIN_SET_MAX {
+            pins.push([i as u8; 32]).unwrap();
+        }
+        assert!(pins.push([0xff; 32]).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Types for signing prehashed messages with Ed25519 keys
+//!
+//! The chip only implements PureEdDSA, so
+//! [crate::Tropic01::eddsa_sign_prehashed] signs the SHA-256 digest of the
+//! message as the Ed25519 message. This is not Ed25519ph as specified in
+//! RFC 8032: the resulting signature verifies against the 32 byte digest,
+//! never against the original message. The types in this module keep the
+//! digest attached to the signature so the two can not be mixed up.
+
+use sha2::Digest as _;
+use sha2::Sha256;
+
+/// SHA-256 digest of a message to be signed with
+/// [crate::Tropic01::eddsa_sign_prehashed].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Prehash([u8; 32]);
+
+impl Prehash {
+    /// Hash `msg` with SHA-256.
+    #[must_use]
+    pub fn digest(msg: &[u8]) -> Self {
+        Self(Sha256::digest(msg).into())
+    }
+
+    /// Use an already computed SHA-256 digest.
+    #[must_use]
+    pub const fn from_digest(digest: [u8; 32]) -> Self {
+        Self(digest)
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<Sha256> for Prehash {
+    /// Finalize an incrementally computed hash, e.g. of a message exceeding
+    /// the max size of [crate::Tropic01::eddsa_sign].
+    fn from(hasher: Sha256) -> Self {
+        Self(hasher.finalize().into())
+    }
+}
+
+/// Ed25519 signature over a [Prehash].
+///
+/// Verify [Self::signature] against [Self::signed_message], i.e. the digest,
+/// not against the original message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PrehashedSignature {
+    prehash: Prehash,
+    signature: [u8; 64],
+}
+
+impl PrehashedSignature {
+    pub(crate) const fn new(prehash: Prehash, signature: [u8; 64]) -> Self {
+        Self { prehash, signature }
+    }
+
+    #[must_use]
+    pub const fn prehash(&self) -> &Prehash {
+        &self.prehash
+    }
+
+    /// Return the message the chip signed, i.e. the bytes of the digest.
+    #[must_use]
+    pub const fn signed_message(&self) -> &[u8; 32] {
+        self.prehash.as_bytes()
+    }
+
+    /// Return the raw `R || S` encoded Ed25519 signature.
+    #[must_use]
+    pub const fn signature(&self) -> &[u8; 64] {
+        &self.signature
+    }
+
+    /// Check whether this signature was made over the digest of `msg`.
+    ///
+    /// This does not verify the signature itself.
+    #[must_use]
+    pub fn is_over(&self, msg: &[u8]) -> bool {
+        Prehash::digest(msg) == self.prehash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sha2::Digest as _;
+    use sha2::Sha256;
+
+    use crate::prehash::Prehash;
+    use crate::prehash::PrehashedSignature;
+
+    #[test]
+    fn prehash_works() {
+        let mut hasher = Sha256::new();
+        hasher.update(b"hello ");
+        hasher.update(b"tropic");
+        let prehash = Prehash::from(hasher);
+        assert_eq!(prehash, Prehash::digest(b"hello tropic"));
+
+        let signature = PrehashedSignature::new(prehash, [0x5a; 64]);
+        assert!(signature.is_over(b"hello tropic"));
+        assert!(!signature.is_over(b"hello"));
+        assert_eq!(signature.signed_message(), prehash.as_bytes());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::Error;
+use crate::L1_POLL_DELAY_NS;
+use crate::L1_READ_MAX_TRIES;
+
+/// Controls how the L1 layer polls the chip while it is busy.
+///
+/// Before each read and write, the chip status is polled until the chip is
+/// ready. After `max_tries` unsuccessful polls [Error::ChipBusy] is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BusyPoll {
+    max_tries: u16,
+    delay_ns: u32,
+}
+
+impl BusyPoll {
+    /// Poll the chip up to `max_tries` times, waiting `delay_ns` nanoseconds
+    /// between polls.
+    #[must_use]
+    pub const fn new(max_tries: u16, delay_ns: u32) -> Self {
+        Self {
+            max_tries,
+            delay_ns,
+        }
+    }
+
+    #[must_use]
+    pub const fn max_tries(&self) -> u16 {
+        self.max_tries
+    }
+
+    #[must_use]
+    pub const fn delay_ns(&self) -> u32 {
+        self.delay_ns
+    }
+}
+
+impl Default for BusyPoll {
+    /// 50 polls, 25ms apart
+    fn default() -> Self {
+        Self::new(L1_READ_MAX_TRIES, L1_POLL_DELAY_NS)
+    }
+}
+
+/// Classes of L3 commands with similar execution times on the chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CommandClass {
+    /// Commands the chip answers quickly, e.g. ping or reading a public key
+    Fast,
+    /// ECDSA and EdDSA signing
+    Sign,
+    /// Generating a new key pair
+    KeyGeneration,
+}
+
+/// Busy-poll budgets per [CommandClass].
+///
+/// While the chip executes an L3 command, the result is polled according to
+/// the budget of the command's class. Classes without a budget use the
+/// [BusyPoll] configured via [crate::Tropic01::with_busy_poll], which is the
+/// default for all classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeoutProfile {
+    fast: Option<BusyPoll>,
+    sign: Option<BusyPoll>,
+    key_generation: Option<BusyPoll>,
+}
+
+impl TimeoutProfile {
+    /// A profile using the general [BusyPoll] for all classes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            fast: None,
+            sign: None,
+            key_generation: None,
+        }
+    }
+
+    /// Poll the result of commands of `class` according to `busy_poll`.
+    #[must_use]
+    pub const fn with_busy_poll(mut self, class: CommandClass, busy_poll: BusyPoll) -> Self {
+        match class {
+            CommandClass::Fast => self.fast = Some(busy_poll),
+            CommandClass::Sign => self.sign = Some(busy_poll),
+            CommandClass::KeyGeneration => self.key_generation = Some(busy_poll),
+        }
+        self
+    }
+
+    /// Return the budget of `class`, if one was set.
+    #[must_use]
+    pub const fn busy_poll(&self, class: CommandClass) -> Option<BusyPoll> {
+        match class {
+            CommandClass::Fast => self.fast,
+            CommandClass::Sign => self.sign,
+            CommandClass::KeyGeneration => self.key_generation,
+        }
+    }
+}
+
+/// Controls whether and how often failed L2 requests are retried.
+///
+/// The policy applies to requests that can be safely repeated as a whole,
+/// i.e. all L2 requests except the chunks of encrypted L3 commands. By default
+/// no request is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryPolicy {
+    max_attempts: u8,
+    backoff_ns: u32,
+    chip_busy: bool,
+    invalid_crc: bool,
+    bus_error: bool,
+}
+
+impl RetryPolicy {
+    /// A policy which does not retry any request.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ns: 0,
+            chip_busy: false,
+            invalid_crc: false,
+            bus_error: false,
+        }
+    }
+
+    /// Try each request at most `max_attempts` times, including the first
+    /// attempt. Values below 1 are treated as 1.
+    #[must_use]
+    pub const fn with_max_attempts(mut self, max_attempts: u8) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Wait `backoff_ns` nanoseconds before each retry.
+    #[must_use]
+    pub const fn with_backoff_ns(mut self, backoff_ns: u32) -> Self {
+        self.backoff_ns = backoff_ns;
+        self
+    }
+
+    /// Retry requests failing with [Error::ChipBusy].
+    #[must_use]
+    pub const fn retry_chip_busy(mut self, retry: bool) -> Self {
+        self.chip_busy = retry;
+        self
+    }
+
+    /// Retry requests failing with [Error::InvalidCRC].
+    #[must_use]
+    pub const fn retry_invalid_crc(mut self, retry: bool) -> Self {
+        self.invalid_crc = retry;
+        self
+    }
+
+    /// Retry requests failing with [Error::BusError], e.g. transient IO errors
+    /// of the SPI device.
+    #[must_use]
+    pub const fn retry_bus_error(mut self, retry: bool) -> Self {
+        self.bus_error = retry;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_attempts(&self) -> u8 {
+        self.max_attempts
+    }
+
+    #[must_use]
+    pub const fn backoff_ns(&self) -> u32 {
+        self.backoff_ns
+    }
+
+    /// Check whether a request failing with `err` should be retried.
+    #[must_use]
+    pub const fn is_retryable<ESpi, EGpio>(&self, err: &Error<ESpi, EGpio>) -> bool {
+        match err {
+            Error::ChipBusy => self.chip_busy,
+            Error::InvalidCRC => self.invalid_crc,
+            Error::BusError(_) => self.bus_error,
+            _ => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+    use crate::retry::BusyPoll;
+    use crate::retry::CommandClass;
+    use crate::retry::RetryPolicy;
+    use crate::retry::TimeoutProfile;
+
+    #[test]
+    fn busy_poll_default_works() {
+        let busy_poll = BusyPoll::default();
+        assert_eq!(busy_poll.max_tries(), 50);
+        assert_eq!(busy_poll.delay_ns(), 25_000_000);
+    }
+
+    #[test]
+    fn timeout_profile_works() {
+        let slow = BusyPoll::new(200, 25_000_000);
+        let profile = TimeoutProfile::new().with_busy_poll(CommandClass::KeyGeneration, slow);
+        assert_eq!(profile.busy_poll(CommandClass::KeyGeneration), Some(slow));
+        assert_eq!(profile.busy_poll(CommandClass::Fast), None);
+        assert_eq!(profile.busy_poll(CommandClass::Sign), None);
+    }
+
+    #[test]
+    fn retry_policy_works() {
+        let policy = RetryPolicy::new();
+        assert!(!policy.is_retryable(&Error::<(), ()>::ChipBusy));
+
+        let policy = policy.retry_chip_busy(true).retry_bus_error(true);
+        assert!(policy.is_retryable(&Error::<(), ()>::ChipBusy));
+        assert!(policy.is_retryable(&Error::<(), ()>::BusError(())));
+        assert!(!policy.is_retryable(&Error::<(), ()>::InvalidCRC));
+        assert!(!policy.is_retryable(&Error::<(), ()>::Unauthorized));
+    }
+}
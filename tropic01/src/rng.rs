@@ -0,0 +1,236 @@
+//! [rand_core] adapter for the chip's true random number generator
+
+use core::mem;
+use core::num::NonZeroU32;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice;
+use rand_core::CryptoRng;
+use rand_core::RngCore;
+use zeroize::Zeroize;
+
+use crate::Tropic01;
+
+/// Max number of random bytes the chip returns per request
+const RANDOM_VALUE_MAX: usize = u8::MAX as usize;
+
+/// Error code returned when the chip could not provide random data
+///
+/// The error of the driver itself can not be passed through [rand_core::Error]
+/// in `no_std` environments.
+pub const RNG_ERROR_CODE: u32 = rand_core::Error::CUSTOM_START;
+
+/// Random number generator backed by [Tropic01::get_random_value].
+///
+/// Random bytes are fetched from the chip in batches of up to 255 bytes and
+/// buffered. A secure session needs to be established before use, see
+/// [Tropic01::session_start].
+///
+/// # Panics
+///
+/// The infallible [RngCore] methods, e.g. [RngCore::fill_bytes], panic if
+/// the chip can not provide random data. Use [RngCore::try_fill_bytes] to
+/// handle errors instead.
+pub struct TropicRng<'a, SPI, CS> {
+    tropic01: &'a mut Tropic01<SPI, CS>,
+    buf: [u8; RANDOM_VALUE_MAX],
+    pos: usize,
+    len: usize,
+}
+
+impl<'a, SPI: SpiDevice, CS: OutputPin> TropicRng<'a, SPI, CS> {
+    pub const fn new(tropic01: &'a mut Tropic01<SPI, CS>) -> Self {
+        Self {
+            tropic01,
+            buf: [0; RANDOM_VALUE_MAX],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    fn refill(&mut self) -> Result<(), rand_core::Error> {
+        let random = self
+            .tropic01
+            .get_random_value(RANDOM_VALUE_MAX as u8)
+            .map_err(|_| rng_error())?;
+        if random.is_empty() {
+            return Err(rng_error());
+        }
+        self.buf
+            .get_mut(..random.len())
+            .ok_or_else(rng_error)?
+            .copy_from_slice(random);
+        self.pos = 0;
+        self.len = random.len();
+        Ok(())
+    }
+}
+
+impl<SPI: SpiDevice, CS: OutputPin> RngCore for TropicRng<'_, SPI, CS> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Fill `dest` with random data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chip can not provide random data, use
+    /// [Self::try_fill_bytes] to handle this case.
+    #[expect(clippy::expect_used)]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("chip to provide random data");
+    }
+
+    fn try_fill_bytes(&mut self, mut dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        while !dest.is_empty() {
+            if self.pos == self.len {
+                self.refill()?;
+            }
+            let buffered = self.buf.get_mut(self.pos..self.len).ok_or_else(rng_error)?;
+            let n = buffered.len().min(dest.len());
+            let (head, tail) = mem::take(&mut dest).split_at_mut(n);
+            // Handed out bytes are not kept
+            for (byte, random) in head.iter_mut().zip(buffered) {
+                *byte = mem::take(random);
+            }
+            dest = tail;
+            self.pos += n;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI: SpiDevice, CS: OutputPin> CryptoRng for TropicRng<'_, SPI, CS> {}
+
+impl<SPI, CS> TropicRng<'_, SPI, CS> {
+    /// Discard the buffered random bytes.
+    fn clear(&mut self) {
+        self.buf.zeroize();
+        self.pos = 0;
+        self.len = 0;
+    }
+}
+
+impl<SPI, CS> Drop for TropicRng<'_, SPI, CS> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn rng_error() -> rand_core::Error {
+    const CODE: NonZeroU32 = NonZeroU32::new(RNG_ERROR_CODE).unwrap();
+    CODE.into()
+}
+
+#[cfg(test)]
+mod test {
+    use core::cell::Cell;
+
+    use dummy_pin::DummyPin;
+    use rand_core::RngCore;
+
+    use crate::Tropic01;
+    use crate::mock::Handler;
+    use crate::mock::MockChip;
+    use crate::mock::RESULT_FAIL;
+    use crate::mock::RESULT_OK;
+    use crate::rng::RANDOM_VALUE_MAX;
+    use crate::rng::RNG_ERROR_CODE;
+    use crate::rng::TropicRng;
+
+    /// Return a mock chip answering random value requests with consecutive
+    /// byte values, counting the requests in `requests`.
+    fn counting_chip(requests: &Cell<u32>) -> Tropic01<MockChip<impl Handler>, DummyPin> {
+        let mut next = 0u8;
+        MockChip::session(move |cmd, res| {
+            assert_eq!(cmd[0], 0x50);
+            requests.set(requests.get() + 1);
+            res.try_extend_from_slice(&[RESULT_OK, 0, 0, 0]).unwrap();
+            for _ in 0..cmd[1] {
+                res.push(next);
+                next = next.wrapping_add(1);
+            }
+        })
+    }
+
+    #[test]
+    fn rng_buffers_random_values_works() {
+        let requests = Cell::new(0);
+        let mut tropic01 = counting_chip(&requests);
+        let mut rng = TropicRng::new(&mut tropic01);
+
+        let mut dest = [0; 10];
+        rng.fill_bytes(&mut dest);
+        assert_eq!(dest, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(requests.get(), 1);
+        // Handed out bytes are not kept
+        assert_eq!(rng.buf[..10], [0; 10]);
+
+        let mut dest = [0; RANDOM_VALUE_MAX - 10];
+        rng.fill_bytes(&mut dest);
+        assert_eq!(dest[0], 10);
+        assert_eq!(dest[RANDOM_VALUE_MAX - 11], 254);
+        assert_eq!(requests.get(), 1);
+
+        // The next call refills the buffer
+        assert_eq!(rng.next_u32(), u32::from_le_bytes([255, 0, 1, 2]));
+        assert_eq!(requests.get(), 2);
+    }
+
+    #[test]
+    fn rng_large_requests_works() {
+        let requests = Cell::new(0);
+        let mut tropic01 = counting_chip(&requests);
+        let mut rng = TropicRng::new(&mut tropic01);
+
+        let mut dest = [0; 3 * RANDOM_VALUE_MAX + 1];
+        rng.try_fill_bytes(&mut dest).unwrap();
+        assert_eq!(requests.get(), 4);
+        for (i, byte) in dest.iter().enumerate() {
+            // 255 is skipped because each request returns 255 bytes
+            assert_eq!(*byte, i as u8);
+        }
+        assert_eq!(rng.len - rng.pos, RANDOM_VALUE_MAX - 1);
+    }
+
+    #[test]
+    fn rng_clear_works() {
+        let requests = Cell::new(0);
+        let mut tropic01 = counting_chip(&requests);
+        let mut rng = TropicRng::new(&mut tropic01);
+
+        rng.next_u64();
+        assert!(rng.buf.iter().any(|byte| *byte != 0));
+        // Called on drop
+        rng.clear();
+        assert_eq!(rng.buf, [0; RANDOM_VALUE_MAX]);
+        assert_eq!(rng.next_u32(), u32::from_le_bytes([255, 0, 1, 2]));
+        assert_eq!(requests.get(), 2);
+    }
+
+    #[test]
+    fn rng_error_works() {
+        let mut tropic01 = MockChip::session(|_, res| res.push(RESULT_FAIL));
+        let mut rng = TropicRng::new(&mut tropic01);
+        let err = rng.try_fill_bytes(&mut [0; 4]).unwrap_err();
+        assert_eq!(err.code().map(u32::from), Some(RNG_ERROR_CODE));
+
+        // Fewer bytes than requested
+        let mut tropic01 = MockChip::session(|_, res| {
+            res.try_extend_from_slice(&[RESULT_OK, 0, 0, 0, 0x5a])
+                .unwrap();
+        });
+        let mut rng = TropicRng::new(&mut tropic01);
+        assert!(rng.try_fill_bytes(&mut [0; 4]).is_err());
+    }
+}
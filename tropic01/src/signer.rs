@@ -0,0 +1,359 @@
+//! `signature` trait implementations for keys stored in the chip's ECC slots
+
+use core::cell::RefCell;
+
+use embedded_hal::digital::ErrorType as GpioErrorType;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::ErrorType as SpiErrorType;
+use embedded_hal::spi::SpiDevice;
+#[cfg(feature = "p256")]
+use sha2::Digest as _;
+#[cfg(feature = "p256")]
+use sha2::Sha256;
+use zerocopy::big_endian::U16;
+
+use crate::Error;
+use crate::SlotPublicKey;
+use crate::Tropic01;
+
+/// Ed25519 signer backed by the key in one of the chip's ECC slots.
+///
+/// Implements [ed25519::signature::Signer], signing with
+/// [Tropic01::eddsa_sign]. A secure session needs to be established before
+/// use, see [Tropic01::session_start].
+#[cfg(feature = "ed25519")]
+pub struct SlotSigner<'a, SPI, CS> {
+    tropic01: RefCell<&'a mut Tropic01<SPI, CS>>,
+    slot: U16,
+    verifying_key: [u8; 32],
+}
+
+#[cfg(feature = "ed25519")]
+impl<'a, SPI: SpiDevice, CS: OutputPin> SlotSigner<'a, SPI, CS> {
+    /// Create a signer for the key in `slot`.
+    ///
+    /// Reads the public key from the chip, the key needs to be an Ed25519 key.
+    pub fn new(
+        tropic01: &'a mut Tropic01<SPI, CS>,
+        slot: U16,
+    ) -> Result<Self, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        let SlotPublicKey::Ed25519(verifying_key) = tropic01.slot_public_key(slot)? else {
+            return Err(Error::InvalidCurve);
+        };
+        Ok(Self {
+            tropic01: RefCell::new(tropic01),
+            slot,
+            verifying_key,
+        })
+    }
+
+    /// Return the slot of the key.
+    #[must_use]
+    pub const fn slot(&self) -> U16 {
+        self.slot
+    }
+
+    /// Release the borrowed [Tropic01].
+    pub const fn into_inner(self) -> &'a mut Tropic01<SPI, CS> {
+        self.tropic01.into_inner()
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl<SPI: SpiDevice, CS: OutputPin> ed25519::signature::Keypair for SlotSigner<'_, SPI, CS> {
+    /// The raw Ed25519 public key
+    type VerifyingKey = [u8; 32];
+
+    fn verifying_key(&self) -> Self::VerifyingKey {
+        self.verifying_key
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl<SPI: SpiDevice, CS: OutputPin> ed25519::signature::Signer<ed25519::Signature>
+    for SlotSigner<'_, SPI, CS>
+{
+    fn try_sign(&self, msg: &[u8]) -> Result<ed25519::Signature, ed25519::signature::Error> {
+        let mut tropic01 = self
+            .tropic01
+            .try_borrow_mut()
+            .map_err(|_| ed25519::signature::Error::new())?;
+        let signature = tropic01
+            .eddsa_sign(self.slot, msg)
+            .map_err(|_| ed25519::signature::Error::new())?;
+        Ok(ed25519::Signature::from_bytes(signature))
+    }
+}
+
+/// ECDSA P-256 signer backed by the key in one of the chip's ECC slots.
+///
+/// Implements [p256::ecdsa::signature::Signer] for fixed-size and DER encoded
+/// signatures, hashing the message with SHA-256 and signing the hash with
+/// [Tropic01::ecdsa_sign]. Already hashed messages can be signed via
+/// [p256::ecdsa::signature::hazmat::PrehashSigner]. A secure session needs to
+/// be established before use, see [Tropic01::session_start].
+#[cfg(feature = "p256")]
+pub struct P256SlotSigner<'a, SPI, CS> {
+    tropic01: RefCell<&'a mut Tropic01<SPI, CS>>,
+    slot: U16,
+    verifying_key: p256::ecdsa::VerifyingKey,
+}
+
+#[cfg(feature = "p256")]
+impl<'a, SPI: SpiDevice, CS: OutputPin> P256SlotSigner<'a, SPI, CS> {
+    /// Create a signer for the key in `slot`.
+    ///
+    /// Reads the public key from the chip, the key needs to be a P-256 key
+    /// which is a valid curve point.
+    pub fn new(
+        tropic01: &'a mut Tropic01<SPI, CS>,
+        slot: U16,
+    ) -> Result<Self, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>> {
+        let SlotPublicKey::P256(key) = tropic01.slot_public_key(slot)? else {
+            return Err(Error::InvalidCurve);
+        };
+        let (x, y) = key.split_at(32);
+        let point = p256::EncodedPoint::from_affine_coordinates(x.into(), y.into(), false);
+        let verifying_key = p256::ecdsa::VerifyingKey::from_encoded_point(&point)
+            .map_err(|_| Error::InvalidPublicKey)?;
+        Ok(Self {
+            tropic01: RefCell::new(tropic01),
+            slot,
+            verifying_key,
+        })
+    }
+
+    /// Return the slot of the key.
+    #[must_use]
+    pub const fn slot(&self) -> U16 {
+        self.slot
+    }
+
+    /// Release the borrowed [Tropic01].
+    pub const fn into_inner(self) -> &'a mut Tropic01<SPI, CS> {
+        self.tropic01.into_inner()
+    }
+}
+
+#[cfg(feature = "p256")]
+impl<SPI: SpiDevice, CS: OutputPin> p256::ecdsa::signature::Keypair
+    for P256SlotSigner<'_, SPI, CS>
+{
+    type VerifyingKey = p256::ecdsa::VerifyingKey;
+
+    fn verifying_key(&self) -> Self::VerifyingKey {
+        self.verifying_key
+    }
+}
+
+#[cfg(feature = "p256")]
+impl<SPI: SpiDevice, CS: OutputPin>
+    p256::ecdsa::signature::hazmat::PrehashSigner<p256::ecdsa::Signature>
+    for P256SlotSigner<'_, SPI, CS>
+{
+    fn sign_prehash(
+        &self,
+        prehash: &[u8],
+    ) -> Result<p256::ecdsa::Signature, p256::ecdsa::signature::Error> {
+        let hash = prehash
+            .try_into()
+            .map_err(|_| p256::ecdsa::signature::Error::new())?;
+        let mut tropic01 = self
+            .tropic01
+            .try_borrow_mut()
+            .map_err(|_| p256::ecdsa::signature::Error::new())?;
+        let signature = tropic01
+            .ecdsa_sign(self.slot, hash)
+            .map_err(|_| p256::ecdsa::signature::Error::new())?;
+        p256::ecdsa::Signature::from_slice(signature)
+    }
+}
+
+#[cfg(feature = "p256")]
+impl<SPI: SpiDevice, CS: OutputPin> p256::ecdsa::signature::Signer<p256::ecdsa::Signature>
+    for P256SlotSigner<'_, SPI, CS>
+{
+    fn try_sign(
+        &self,
+        msg: &[u8],
+    ) -> Result<p256::ecdsa::Signature, p256::ecdsa::signature::Error> {
+        use p256::ecdsa::signature::hazmat::PrehashSigner as _;
+
+        self.sign_prehash(&Sha256::digest(msg))
+    }
+}
+
+#[cfg(feature = "p256")]
+impl<SPI: SpiDevice, CS: OutputPin> p256::ecdsa::signature::Signer<p256::ecdsa::DerSignature>
+    for P256SlotSigner<'_, SPI, CS>
+{
+    fn try_sign(
+        &self,
+        msg: &[u8],
+    ) -> Result<p256::ecdsa::DerSignature, p256::ecdsa::signature::Error> {
+        let signature: p256::ecdsa::Signature = self.try_sign(msg)?;
+        Ok(signature.to_der())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dummy_pin::DummyPin;
+    use zerocopy::big_endian::U16;
+
+    use crate::Error;
+    use crate::Tropic01;
+    use crate::mock::Handler;
+    use crate::mock::MockChip;
+    use crate::mock::RESULT_FAIL;
+    use crate::mock::RESULT_OK;
+    #[cfg(feature = "p256")]
+    use crate::signer::P256SlotSigner;
+    #[cfg(feature = "ed25519")]
+    use crate::signer::SlotSigner;
+
+    const ED25519_SLOT: U16 = U16::new(1);
+    #[cfg(feature = "p256")]
+    const P256_SLOT: U16 = U16::new(2);
+    /// Slot with a P-256 key which is not a curve point
+    const INVALID_P256_SLOT: U16 = U16::new(3);
+    const ED25519_KEY: [u8; 32] = [0x01; 32];
+    #[cfg(feature = "p256")]
+    const P256_SECRET: [u8; 32] = [0x07; 32];
+    const SIGNATURE: [u8; 64] = [0x5a; 64];
+    const MESSAGE: &[u8] = b"message to be signed by the chip";
+
+    /// Return a mock chip with an Ed25519 key in [ED25519_SLOT] and the
+    /// P-256 key [P256_SECRET] in [P256_SLOT].
+    ///
+    /// EdDSA signing checks that the message is [MESSAGE], ECDSA signing signs
+    /// the received hash.
+    fn signing_chip() -> Tropic01<MockChip<impl Handler>, DummyPin> {
+        MockChip::session(|cmd, res| {
+            #[cfg(feature = "p256")]
+            let p256_key = p256::ecdsa::SigningKey::from_bytes(&P256_SECRET.into()).unwrap();
+            res.push(RESULT_OK);
+            let slot = U16::new(u16::from_be_bytes([cmd[1], cmd[2]]));
+            match cmd[0] {
+                // EccKeyRead
+                0x62 if slot == ED25519_SLOT => {
+                    res.try_extend_from_slice(&[0x02, 0x01]).unwrap();
+                    res.try_extend_from_slice(&[0; 13]).unwrap();
+                    res.try_extend_from_slice(&ED25519_KEY).unwrap();
+                },
+                #[cfg(feature = "p256")]
+                0x62 if slot == P256_SLOT => {
+                    let point = p256_key.verifying_key().to_encoded_point(false);
+                    res.try_extend_from_slice(&[0x01, 0x01]).unwrap();
+                    res.try_extend_from_slice(&[0; 13]).unwrap();
+                    res.try_extend_from_slice(&point.as_bytes()[1..]).unwrap();
+                },
+                0x62 if slot == INVALID_P256_SLOT => {
+                    res.try_extend_from_slice(&[0x01, 0x01]).unwrap();
+                    res.try_extend_from_slice(&[0; 13]).unwrap();
+                    res.try_extend_from_slice(&[0xff; 64]).unwrap();
+                },
+                // EcDSASign
+                #[cfg(feature = "p256")]
+                0x70 => {
+                    use p256::ecdsa::signature::hazmat::PrehashSigner as _;
+
+                    assert_eq!(slot, P256_SLOT);
+                    assert_eq!(cmd.len(), 48);
+                    let signature: p256::ecdsa::Signature =
+                        p256_key.sign_prehash(&cmd[16..]).unwrap();
+                    res.try_extend_from_slice(&[0; 15]).unwrap();
+                    res.try_extend_from_slice(&signature.to_bytes()).unwrap();
+                },
+                // EdDSASign
+                0x71 => {
+                    assert_eq!(slot, ED25519_SLOT);
+                    assert_eq!(&cmd[16..], MESSAGE);
+                    res.try_extend_from_slice(&[0; 15]).unwrap();
+                    res.try_extend_from_slice(&SIGNATURE).unwrap();
+                },
+                _ => res[0] = RESULT_FAIL,
+            }
+        })
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn slot_signer_works() {
+        use ed25519::signature::Keypair as _;
+        use ed25519::signature::Signer as _;
+
+        let mut tropic01 = signing_chip();
+        let signer = SlotSigner::new(&mut tropic01, ED25519_SLOT).unwrap();
+        assert_eq!(signer.slot(), ED25519_SLOT);
+        assert_eq!(signer.verifying_key(), ED25519_KEY);
+        let signature = signer.try_sign(MESSAGE).unwrap();
+        assert_eq!(signature.to_bytes(), SIGNATURE);
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn slot_signer_invalid_curve_works() {
+        let mut tropic01 = signing_chip();
+        assert!(matches!(
+            SlotSigner::new(&mut tropic01, INVALID_P256_SLOT),
+            Err(Error::InvalidCurve)
+        ));
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn p256_slot_signer_works() {
+        use p256::ecdsa::signature::Keypair as _;
+        use p256::ecdsa::signature::Signer as _;
+        use p256::ecdsa::signature::Verifier as _;
+        use p256::ecdsa::signature::hazmat::PrehashSigner as _;
+        use p256::ecdsa::signature::hazmat::PrehashVerifier as _;
+        use sha2::Digest as _;
+        use sha2::Sha256;
+
+        let mut tropic01 = signing_chip();
+        let signer = P256SlotSigner::new(&mut tropic01, P256_SLOT).unwrap();
+        assert_eq!(signer.slot(), P256_SLOT);
+        let verifying_key = signer.verifying_key();
+        assert_eq!(
+            verifying_key,
+            *p256::ecdsa::SigningKey::from_bytes(&P256_SECRET.into())
+                .unwrap()
+                .verifying_key()
+        );
+
+        // The message is hashed with SHA-256 before signing
+        let signature: p256::ecdsa::Signature = signer.try_sign(MESSAGE).unwrap();
+        verifying_key.verify(MESSAGE, &signature).unwrap();
+        let signature: p256::ecdsa::DerSignature = signer.try_sign(MESSAGE).unwrap();
+        verifying_key.verify(MESSAGE, &signature).unwrap();
+
+        // Prehashes are signed as is
+        let hash = Sha256::digest(MESSAGE);
+        let signature: p256::ecdsa::Signature = signer.sign_prehash(&hash).unwrap();
+        verifying_key.verify_prehash(&hash, &signature).unwrap();
+        verifying_key.verify(MESSAGE, &signature).unwrap();
+        let prehash = [0x33; 32];
+        let signature: p256::ecdsa::Signature = signer.sign_prehash(&prehash).unwrap();
+        verifying_key.verify_prehash(&prehash, &signature).unwrap();
+        assert!(verifying_key.verify(&prehash, &signature).is_err());
+        // Only SHA-256 sized prehashes are supported
+        assert!(signer.sign_prehash(&[0x33; 48]).is_err());
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn p256_slot_signer_invalid_key_works() {
+        let mut tropic01 = signing_chip();
+        assert!(matches!(
+            P256SlotSigner::new(&mut tropic01, ED25519_SLOT),
+            Err(Error::InvalidCurve)
+        ));
+        let mut tropic01 = signing_chip();
+        assert!(matches!(
+            P256SlotSigner::new(&mut tropic01, INVALID_P256_SLOT),
+            Err(Error::InvalidPublicKey)
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+//! SSH signatures made with Ed25519 keys in the chip's ECC slots
+//!
+//! The signatures use the `SSHSIG` format of OpenSSH (see `PROTOCOL.sshsig`),
+//! as produced by `ssh-keygen -Y sign`. Git accepts them for commits and tags
+//! with `gpg.format = ssh`.
+
+use core::fmt;
+
+use aes_gcm::aead::arrayvec::ArrayVec;
+use embedded_hal::digital::ErrorType as GpioErrorType;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::ErrorType as SpiErrorType;
+use embedded_hal::spi::SpiDevice;
+use sha2::Digest as _;
+use sha2::Sha512;
+use zerocopy::big_endian::U16;
+
+use crate::Error;
+use crate::SlotPublicKey;
+use crate::Tropic01;
+use crate::lt_2::base64_chunk;
+
+const MAGIC_PREAMBLE: &[u8; 6] = b"SSHSIG";
+const SIG_VERSION: u32 = 1;
+const KEY_TYPE: &[u8] = b"ssh-ed25519";
+const HASH_ALGORITHM: &[u8] = b"sha512";
+/// Max length of the namespace passed to [Tropic01::ssh_sign]
+pub const SSH_NAMESPACE_MAX_LEN: usize = 64;
+/// Max size of the data signed by the chip
+const SIGNED_DATA_MAX_SIZE: usize =
+    MAGIC_PREAMBLE.len() + 4 * 4 + SSH_NAMESPACE_MAX_LEN + HASH_ALGORITHM.len() + 64;
+/// Number of base64 characters per line of the armored signature
+const LINE_LEN: usize = 70;
+
+/// Signature in the `SSHSIG` format, see [Tropic01::ssh_sign].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SshSignature<'a> {
+    public_key: [u8; 32],
+    namespace: &'a str,
+    signature: [u8; 64],
+}
+
+impl<'a> SshSignature<'a> {
+    /// Wrap the Ed25519 `signature` of the data returned by
+    /// [ssh_signed_data].
+    #[must_use]
+    pub const fn new(public_key: [u8; 32], namespace: &'a str, signature: [u8; 64]) -> Self {
+        Self {
+            public_key,
+            namespace,
+            signature,
+        }
+    }
+
+    #[must_use]
+    pub const fn public_key(&self) -> &[u8; 32] {
+        &self.public_key
+    }
+
+    #[must_use]
+    pub const fn namespace(&self) -> &'a str {
+        self.namespace
+    }
+
+    /// Return the raw `R || S` encoded Ed25519 signature.
+    #[must_use]
+    pub const fn signature(&self) -> &[u8; 64] {
+        &self.signature
+    }
+
+    /// Write the public key in the format of OpenSSH public key files, e.g.
+    /// for the `allowed_signers` file used to verify signatures.
+    pub fn write_public_key<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("ssh-ed25519 ")?;
+        let mut b64 = Base64Writer::new(w, usize::MAX);
+        write_public_key_blob(&mut b64, self.public_key)?;
+        b64.finish()
+    }
+
+    /// Write the armored signature as written by `ssh-keygen -Y sign`.
+    pub fn write_armored<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("-----BEGIN SSH SIGNATURE-----\n")?;
+        let mut b64 = Base64Writer::new(w, LINE_LEN);
+        b64.write(MAGIC_PREAMBLE)?;
+        b64.write(&SIG_VERSION.to_be_bytes())?;
+        b64.write(&ssh_len(4 + KEY_TYPE.len() + 4 + self.public_key.len()))?;
+        write_public_key_blob(&mut b64, self.public_key)?;
+        write_string(&mut b64, self.namespace.as_bytes())?;
+        // reserved
+        write_string(&mut b64, &[])?;
+        write_string(&mut b64, HASH_ALGORITHM)?;
+        b64.write(&ssh_len(4 + KEY_TYPE.len() + 4 + self.signature.len()))?;
+        write_string(&mut b64, KEY_TYPE)?;
+        write_string(&mut b64, &self.signature)?;
+        b64.finish()?;
+        w.write_str("\n-----END SSH SIGNATURE-----\n")
+    }
+}
+
+/// Return the data to sign with Ed25519 for an `SSHSIG` signature of `msg`.
+///
+/// Returns `None` if `namespace` is longer than [SSH_NAMESPACE_MAX_LEN].
+#[must_use]
+pub fn ssh_signed_data(namespace: &str, msg: &[u8]) -> Option<ArrayVec<u8, SIGNED_DATA_MAX_SIZE>> {
+    if namespace.len() > SSH_NAMESPACE_MAX_LEN {
+        return None;
+    }
+    let mut data = ArrayVec::new();
+    data.try_extend_from_slice(MAGIC_PREAMBLE).ok()?;
+    for field in [
+        namespace.as_bytes(),
+        // reserved
+        &[],
+        HASH_ALGORITHM,
+        &Sha512::digest(msg),
+    ] {
+        data.try_extend_from_slice(&ssh_len(field.len())).ok()?;
+        data.try_extend_from_slice(field).ok()?;
+    }
+    Some(data)
+}
+
+impl<SPI: SpiDevice, CS: OutputPin> Tropic01<SPI, CS> {
+    /// Sign `msg` with the Ed25519 key in `slot`, producing an SSH signature
+    /// for `namespace`.
+    ///
+    /// Git signs commits and tags in the `git` namespace. Fails with
+    /// [Error::InvalidCurve] if `slot` holds a P-256 key and with
+    /// [Error::RequestExceedsSize] if `namespace` is longer than
+    /// [SSH_NAMESPACE_MAX_LEN].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(slot = slot.get()))
+    )]
+    pub fn ssh_sign<'n>(
+        &mut self,
+        slot: U16,
+        namespace: &'n str,
+        msg: &[u8],
+    ) -> Result<SshSignature<'n>, Error<<SPI as SpiErrorType>::Error, <CS as GpioErrorType>::Error>>
+    {
+        let SlotPublicKey::Ed25519(public_key) = self.slot_public_key(slot)? else {
+            return Err(Error::InvalidCurve);
+        };
+        let data = ssh_signed_data(namespace, msg).ok_or(Error::RequestExceedsSize)?;
+        let signature = *self.eddsa_sign(slot, &data)?;
+        Ok(SshSignature::new(public_key, namespace, signature))
+    }
+}
+
+/// Encode `len` as the length prefix of an SSH `string`.
+fn ssh_len(len: usize) -> [u8; 4] {
+    u32::try_from(len).unwrap_or(u32::MAX).to_be_bytes()
+}
+
+fn write_string<W: fmt::Write>(w: &mut Base64Writer<'_, W>, bytes: &[u8]) -> fmt::Result {
+    w.write(&ssh_len(bytes.len()))?;
+    w.write(bytes)
+}
+
+fn write_public_key_blob<W: fmt::Write>(
+    w: &mut Base64Writer<'_, W>,
+    public_key: [u8; 32],
+) -> fmt::Result {
+    write_string(w, KEY_TYPE)?;
+    write_string(w, &public_key)
+}
+
+/// Base64 encoder writing lines of up to `line_len` characters.
+struct Base64Writer<'w, W> {
+    w: &'w mut W,
+    pending: ArrayVec<u8, 3>,
+    line_len: usize,
+    column: usize,
+}
+
+impl<'w, W: fmt::Write> Base64Writer<'w, W> {
+    const fn new(w: &'w mut W, line_len: usize) -> Self {
+        Self {
+            w,
+            pending: ArrayVec::new_const(),
+            line_len,
+            column: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> fmt::Result {
+        for byte in bytes {
+            self.pending.push(*byte);
+            if self.pending.is_full() {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the remaining bytes with padding.
+    fn finish(mut self) -> fmt::Result {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        for c in base64_chunk(&self.pending).chars() {
+            if self.column == self.line_len {
+                self.w.write_char('\n')?;
+                self.column = 0;
+            }
+            self.w.write_char(c)?;
+            self.column += 1;
+        }
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aes_gcm::aead::arrayvec::ArrayString;
+
+    use crate::ssh::Base64Writer;
+    use crate::ssh::SSH_NAMESPACE_MAX_LEN;
+    use crate::ssh::SshSignature;
+    use crate::ssh::ssh_signed_data;
+
+    #[test]
+    fn base64_writer_wraps_lines_works() {
+        let mut out = ArrayString::<32>::new();
+        let mut b64 = Base64Writer::new(&mut out, 4);
+        b64.write(b"Man").unwrap();
+        b64.write(b"Ma").unwrap();
+        b64.finish().unwrap();
+        assert_eq!(out.as_str(), "TWFu\nTWE=");
+    }
+
+    #[test]
+    fn ssh_signed_data_works() {
+        let data = ssh_signed_data("git", b"").unwrap();
+        assert_eq!(&data[..6], b"SSHSIG");
+        assert_eq!(&data[6..13], b"\0\0\0\x03git");
+        assert_eq!(&data[13..17], &[0; 4]);
+        assert_eq!(&data[17..27], b"\0\0\0\x06sha512");
+        assert_eq!(&data[27..31], &[0, 0, 0, 64]);
+        // SHA-512 of the empty message
+        assert_eq!(&data[31..35], &[0xcf, 0x83, 0xe1, 0x35]);
+        assert_eq!(data.len(), 31 + 64);
+
+        let namespace = core::str::from_utf8(&[b'n'; SSH_NAMESPACE_MAX_LEN + 1]).unwrap();
+        assert!(ssh_signed_data(namespace, b"").is_none());
+        assert!(ssh_signed_data(&namespace[1..], b"").is_some());
+    }
+
+    #[test]
+    fn ssh_signature_public_key_works() {
+        let signature = SshSignature::new([0; 32], "git", [0; 64]);
+        let mut out = ArrayString::<128>::new();
+        signature.write_public_key(&mut out).unwrap();
+        assert_eq!(
+            out.as_str(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        );
+    }
+
+    #[test]
+    fn ssh_signature_armor_works() {
+        let signature = SshSignature::new([0; 32], "git", [0; 64]);
+        let mut out = ArrayString::<512>::new();
+        signature.write_armored(&mut out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("-----BEGIN SSH SIGNATURE-----"));
+        assert_eq!(
+            lines.next(),
+            Some("U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgAAAAAAAAAAAAAAAAAAAAAAAAAA")
+        );
+        assert!(lines.all(|line| line.len() <= 70));
+        assert!(out.ends_with("\n-----END SSH SIGNATURE-----\n"));
+    }
+}
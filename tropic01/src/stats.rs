@@ -0,0 +1,126 @@
+use crate::Error;
+
+/// Counters of the traffic with the chip and of the errors seen on the way.
+///
+/// Read via [crate::Tropic01::stats]. The counters saturate instead of
+/// wrapping around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransportStats {
+    requests: u32,
+    retries: u32,
+    resends: u32,
+    busy_timeouts: u32,
+    crc_errors: u32,
+    invalid_responses: u32,
+    bus_errors: u32,
+}
+
+impl TransportStats {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            requests: 0,
+            retries: 0,
+            resends: 0,
+            busy_timeouts: 0,
+            crc_errors: 0,
+            invalid_responses: 0,
+            bus_errors: 0,
+        }
+    }
+
+    /// Number of L2 frames written to the chip, including resent frames
+    #[must_use]
+    pub const fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    /// Number of L2 requests repeated according to the [crate::RetryPolicy]
+    #[must_use]
+    pub const fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Number of frames resent because the chip reported a CRC or generic
+    /// error
+    #[must_use]
+    pub const fn resends(&self) -> u32 {
+        self.resends
+    }
+
+    /// Number of times the chip stayed busy for the whole busy-poll budget,
+    /// see [Error::ChipBusy]
+    #[must_use]
+    pub const fn busy_timeouts(&self) -> u32 {
+        self.busy_timeouts
+    }
+
+    /// Number of responses with an invalid CRC, see [Error::InvalidCRC]
+    #[must_use]
+    pub const fn crc_errors(&self) -> u32 {
+        self.crc_errors
+    }
+
+    /// Number of truncated or malformed responses
+    #[must_use]
+    pub const fn invalid_responses(&self) -> u32 {
+        self.invalid_responses
+    }
+
+    /// Number of errors of the SPI device or the chip select pin
+    #[must_use]
+    pub const fn bus_errors(&self) -> u32 {
+        self.bus_errors
+    }
+
+    pub(crate) const fn record_request(&mut self) {
+        self.requests = self.requests.saturating_add(1);
+    }
+
+    pub(crate) const fn record_retry(&mut self) {
+        self.retries = self.retries.saturating_add(1);
+    }
+
+    pub(crate) const fn record_resend(&mut self) {
+        self.resends = self.resends.saturating_add(1);
+    }
+
+    /// Count `err` if it is caused by the transport or the chip's responses.
+    pub(crate) const fn record_error<ESpi, EGpio>(&mut self, err: &Error<ESpi, EGpio>) {
+        let counter = match err {
+            Error::ChipBusy => &mut self.busy_timeouts,
+            Error::InvalidCRC => &mut self.crc_errors,
+            Error::InvalidChipStatus(_) | Error::InvalidL2Response | Error::ParsingError(_) => {
+                &mut self.invalid_responses
+            },
+            Error::BusError(_) | Error::GPIOError(_) => &mut self.bus_errors,
+            _ => return,
+        };
+        *counter = counter.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+    use crate::stats::TransportStats;
+
+    #[test]
+    fn transport_stats_works() {
+        let mut stats = TransportStats::new();
+        stats.record_request();
+        stats.record_retry();
+        stats.record_error(&Error::<(), ()>::ChipBusy);
+        stats.record_error(&Error::<(), ()>::BusError(()));
+        stats.record_error(&Error::<(), ()>::InvalidL2Response);
+        stats.record_error(&Error::<(), ()>::Unauthorized);
+        assert_eq!(stats.requests(), 1);
+        assert_eq!(stats.retries(), 1);
+        assert_eq!(stats.resends(), 0);
+        assert_eq!(stats.busy_timeouts(), 1);
+        assert_eq!(stats.bus_errors(), 1);
+        assert_eq!(stats.invalid_responses(), 1);
+        assert_eq!(stats.crc_errors(), 0);
+    }
+}